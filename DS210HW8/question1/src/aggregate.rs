@@ -0,0 +1,155 @@
+use crate::dataframe::DataFrame;
+use crate::dtype::ColumnVal;
+
+// A reducer over one or more columns. `update` sees one row at a time (one value
+// per requested column), and partial states built on separate chunks of rows are
+// combined with `merge`, so the same reducer works for whole frames, groups,
+// streamed batches and parallel chunks.
+pub trait Aggregator {
+    type State;
+
+    fn init(&self) -> Self::State;
+    fn update(&self, state: &mut Self::State, row: &[&ColumnVal]);
+    fn merge(&self, state: &mut Self::State, other: Self::State);
+    fn finish(&self, state: Self::State) -> ColumnVal;
+}
+
+pub struct Count;
+
+impl Aggregator for Count {
+    type State = i64;
+
+    fn init(&self) -> i64 {
+        0
+    }
+
+    fn update(&self, state: &mut i64, row: &[&ColumnVal]) {
+        if !row[0].is_null() {
+            *state += 1;
+        }
+    }
+
+    fn merge(&self, state: &mut i64, other: i64) {
+        *state += other;
+    }
+
+    fn finish(&self, state: i64) -> ColumnVal {
+        ColumnVal::Four(state)
+    }
+}
+
+pub struct Sum;
+
+impl Aggregator for Sum {
+    type State = f64;
+
+    fn init(&self) -> f64 {
+        0.0
+    }
+
+    fn update(&self, state: &mut f64, row: &[&ColumnVal]) {
+        if let Some(x) = row[0].as_f64() {
+            *state += x;
+        }
+    }
+
+    fn merge(&self, state: &mut f64, other: f64) {
+        *state += other;
+    }
+
+    fn finish(&self, state: f64) -> ColumnVal {
+        ColumnVal::Three(state)
+    }
+}
+
+pub struct Mean;
+
+impl Aggregator for Mean {
+    type State = (f64, usize);
+
+    fn init(&self) -> (f64, usize) {
+        (0.0, 0)
+    }
+
+    fn update(&self, state: &mut (f64, usize), row: &[&ColumnVal]) {
+        if let Some(x) = row[0].as_f64() {
+            state.0 += x;
+            state.1 += 1;
+        }
+    }
+
+    fn merge(&self, state: &mut (f64, usize), other: (f64, usize)) {
+        state.0 += other.0;
+        state.1 += other.1;
+    }
+
+    fn finish(&self, state: (f64, usize)) -> ColumnVal {
+        if state.1 == 0 {
+            ColumnVal::Null
+        } else {
+            ColumnVal::Three(state.0 / state.1 as f64)
+        }
+    }
+}
+
+// NaN has no place in the sorted order, so Median skips it like a null.
+pub struct Median;
+
+impl Aggregator for Median {
+    type State = Vec<f64>;
+
+    fn init(&self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    fn update(&self, state: &mut Vec<f64>, row: &[&ColumnVal]) {
+        if let Some(x) = row[0].as_f64().filter(|x| !x.is_nan()) {
+            state.push(x);
+        }
+    }
+
+    fn merge(&self, state: &mut Vec<f64>, other: Vec<f64>) {
+        state.extend(other);
+    }
+
+    fn finish(&self, mut values: Vec<f64>) -> ColumnVal {
+        if values.is_empty() {
+            return ColumnVal::Null;
+        }
+        values.sort_by(f64::total_cmp);
+        let len = values.len();
+        let median = if len.is_multiple_of(2) {
+            (values[len / 2 - 1] + values[len / 2]) / 2.0
+        } else {
+            values[len / 2]
+        };
+        ColumnVal::Three(median)
+    }
+}
+
+// Feeds batches (for example frames read one file at a time) through an aggregator
+// without keeping the batches around.
+pub struct StreamingAgg<'a, A: Aggregator> {
+    agg: &'a A,
+    labels: Vec<String>,
+    state: A::State,
+}
+
+impl<'a, A: Aggregator> StreamingAgg<'a, A> {
+    pub fn new(agg: &'a A, labels: &[String]) -> Self {
+        StreamingAgg {
+            agg,
+            labels: labels.to_vec(),
+            state: agg.init(),
+        }
+    }
+
+    pub fn feed(&mut self, batch: &DataFrame) {
+        let partial = batch.fold_rows(&self.labels, self.agg, 0..batch.nrows());
+        self.agg.merge(&mut self.state, partial);
+    }
+
+    pub fn finish(self) -> ColumnVal {
+        self.agg.finish(self.state)
+    }
+}
@@ -0,0 +1,401 @@
+// Columnar on-disk cache for a DataFrame. All integers are little-endian.
+//
+//   preamble (32 bytes): magic, version u32, ncols u32, nrows u64, header_len u32,
+//                        crc32 u32 of everything after the preamble
+//   header:              per column: name_len u32, name, dtype u8,
+//                        data_offset u64, data_len u64, null_offset u64 (0 = no nulls)
+//   body:                one 8-byte aligned block per column (plus a validity block
+//                        of one byte per row for columns with nulls)
+//
+// Int and Float columns are stored as raw i64/f64 so they can be borrowed straight
+// out of the mapped file. Bool columns are one byte per row, and Str columns are
+// nrows + 1 u64 offsets followed by the UTF-8 bytes; the offsets are checked when
+// the file is opened, so strings can be borrowed too. Series indexes are not stored.
+
+use std::fs::{self, File};
+use std::io::Write;
+
+use memmap2::Mmap;
+
+use crate::dataframe::DataFrame;
+use crate::dtype::{ColumnVal, DType};
+use crate::error::Error;
+use crate::schema::Schema;
+use crate::series::Series;
+
+const MAGIC: &[u8; 8] = b"DFCOLS\0\0";
+const VERSION: u32 = 1;
+const PREAMBLE_LEN: usize = 32;
+
+struct ColumnEntry {
+    name: String,
+    dtype: DType,
+    offset: usize,
+    len: usize,
+    nulls: Option<usize>,
+}
+
+pub struct MappedFrame {
+    mmap: Mmap,
+    nrows: usize,
+    body_start: usize,
+    entries: Vec<ColumnEntry>,
+}
+
+impl MappedFrame {
+    pub fn open(path: &str) -> Result<MappedFrame, Error> {
+        let file = File::open(path)?;
+        // The cache is only ever written whole by `write`, so we assume nobody
+        // truncates or rewrites it while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < PREAMBLE_LEN || &mmap[0..8] != MAGIC {
+            return Err(Error::Cache(format!("{} is not a column cache", path)));
+        }
+        let version = read_u32(&mmap, 8);
+        if version != VERSION {
+            return Err(Error::Cache(format!(
+                "{} has cache version {}, expected {}",
+                path, version, VERSION
+            )));
+        }
+        let ncols = read_u32(&mmap, 12) as usize;
+        let nrows = read_u64(&mmap, 16) as usize;
+        let header_len = read_u32(&mmap, 24) as usize;
+        let checksum = read_u32(&mmap, 28);
+        if crc32fast::hash(&mmap[PREAMBLE_LEN..]) != checksum {
+            return Err(Error::Cache(format!("{} failed its checksum", path)));
+        }
+
+        let header_end = PREAMBLE_LEN + header_len;
+        let body_start = align8(header_end);
+        if body_start > mmap.len() {
+            return Err(Error::Cache(format!("{} has a truncated header", path)));
+        }
+
+        let mut entries = Vec::with_capacity(ncols);
+        let mut pos = PREAMBLE_LEN;
+        let corrupt = || Error::Cache(format!("{} has a corrupt header", path));
+        for _ in 0..ncols {
+            if pos + 4 > header_end {
+                return Err(corrupt());
+            }
+            let name_len = read_u32(&mmap, pos) as usize;
+            pos += 4;
+            if pos + name_len + 25 > header_end {
+                return Err(corrupt());
+            }
+            let name = String::from_utf8(mmap[pos..pos + name_len].to_vec())
+                .map_err(|_| Error::Cache(format!("{} has a bad column name", path)))?;
+            pos += name_len;
+            let dtype = match mmap[pos] {
+                0 => DType::Str,
+                1 => DType::Bool,
+                2 => DType::Float,
+                3 => DType::Int,
+                code => {
+                    return Err(Error::Cache(format!("{} has unknown dtype {}", path, code)));
+                }
+            };
+            pos += 1;
+            let offset = read_u64(&mmap, pos) as usize;
+            let len = read_u64(&mmap, pos + 8) as usize;
+            let nulls = match read_u64(&mmap, pos + 16) as usize {
+                0 => None,
+                off => Some(off),
+            };
+            pos += 24;
+
+            let expected_len = match dtype {
+                DType::Int | DType::Float => len == nrows * 8,
+                DType::Bool => len == nrows,
+                DType::Str => len >= (nrows + 1) * 8,
+            };
+            let in_bounds = |off: usize, len: usize| body_start + off + len <= mmap.len();
+            if !expected_len
+                || !in_bounds(offset, len)
+                || nulls.is_some_and(|off| !in_bounds(off, nrows))
+            {
+                return Err(Error::Cache(format!(
+                    "{} column {} is truncated",
+                    path, name
+                )));
+            }
+            let data = &mmap[body_start + offset..body_start + offset + len];
+            if dtype == DType::Str && !string_offsets_valid(data, nrows) {
+                return Err(Error::Cache(format!(
+                    "{} column {} has bad string offsets",
+                    path, name
+                )));
+            }
+            entries.push(ColumnEntry {
+                name,
+                dtype,
+                offset,
+                len,
+                nulls,
+            });
+        }
+        if pos != header_end {
+            return Err(corrupt());
+        }
+
+        Ok(MappedFrame {
+            mmap,
+            nrows,
+            body_start,
+            entries,
+        })
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn schema(&self) -> Schema {
+        self.entries
+            .iter()
+            .fold(Schema::new(), |schema, e| schema.field(&e.name, e.dtype))
+    }
+
+    // Borrows a Float column directly from the mapped file. Null rows read as 0.0;
+    // check `validity` if the column may contain nulls.
+    pub fn float_column(&self, name: &str) -> Option<&[f64]> {
+        let entry = self.entry(name, DType::Float)?;
+        cast_slice(self.block(entry.offset, entry.len))
+    }
+
+    pub fn int_column(&self, name: &str) -> Option<&[i64]> {
+        let entry = self.entry(name, DType::Int)?;
+        cast_slice(self.block(entry.offset, entry.len))
+    }
+
+    // One byte per row, 1 for true.
+    pub fn bool_column(&self, name: &str) -> Option<&[u8]> {
+        let entry = self.entry(name, DType::Bool)?;
+        Some(self.block(entry.offset, entry.len))
+    }
+
+    // Borrows one string of a Str column. Null rows read as "".
+    pub fn str_value(&self, name: &str, row: usize) -> Option<&str> {
+        let entry = self.entry(name, DType::Str)?;
+        if row >= self.nrows {
+            return None;
+        }
+        let data = self.block(entry.offset, entry.len);
+        let bytes = &data[(self.nrows + 1) * 8..];
+        let start = read_u64(data, row * 8) as usize;
+        let end = read_u64(data, (row + 1) * 8) as usize;
+        std::str::from_utf8(&bytes[start..end]).ok()
+    }
+
+    // One byte per row, 1 for a value and 0 for null. None when the column has no nulls.
+    pub fn validity(&self, name: &str) -> Option<&[u8]> {
+        let entry = self.entries.iter().find(|e| e.name == name)?;
+        entry.nulls.map(|off| self.block(off, self.nrows))
+    }
+
+    pub fn column(&self, name: &str) -> Option<Series> {
+        let entry = self.entries.iter().find(|e| e.name == name)?;
+        let data = self.block(entry.offset, entry.len);
+        let mut values: Vec<ColumnVal> = match entry.dtype {
+            DType::Int => (0..self.nrows)
+                .map(|i| ColumnVal::Four(read_u64(data, i * 8) as i64))
+                .collect(),
+            DType::Float => (0..self.nrows)
+                .map(|i| ColumnVal::Three(f64::from_bits(read_u64(data, i * 8))))
+                .collect(),
+            DType::Bool => data.iter().map(|&b| ColumnVal::Two(b != 0)).collect(),
+            DType::Str => {
+                let bytes = &data[(self.nrows + 1) * 8..];
+                (0..self.nrows)
+                    .map(|i| {
+                        let start = read_u64(data, i * 8) as usize;
+                        let end = read_u64(data, (i + 1) * 8) as usize;
+                        ColumnVal::One(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+                    })
+                    .collect()
+            }
+        };
+        if let Some(validity) = self.validity(name) {
+            for (val, &valid) in values.iter_mut().zip(validity) {
+                if valid == 0 {
+                    *val = ColumnVal::Null;
+                }
+            }
+        }
+        Some(Series::with_dtype(name, entry.dtype, values))
+    }
+
+    pub fn to_dataframe(&self) -> DataFrame {
+        DataFrame::from_series(
+            self.entries
+                .iter()
+                .filter_map(|e| self.column(&e.name))
+                .collect(),
+        )
+    }
+
+    fn entry(&self, name: &str, dtype: DType) -> Option<&ColumnEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name && e.dtype == dtype)
+    }
+
+    fn block(&self, offset: usize, len: usize) -> &[u8] {
+        let start = self.body_start + offset;
+        &self.mmap[start..start + len]
+    }
+}
+
+pub fn write(df: &DataFrame, path: &str) -> Result<(), Error> {
+    let nrows = df.nrows();
+    let mut header = Vec::new();
+    let mut body = Vec::new();
+
+    for label in df.labels() {
+        let col = df.column(label).unwrap();
+
+        pad8(&mut body);
+        let offset = body.len();
+        match col.dtype() {
+            DType::Int => {
+                for val in col.values() {
+                    let x = if let ColumnVal::Four(x) = val { *x } else { 0 };
+                    body.extend_from_slice(&x.to_le_bytes());
+                }
+            }
+            DType::Float => {
+                for val in col.values() {
+                    let x = if let ColumnVal::Three(x) = val {
+                        *x
+                    } else {
+                        0.0
+                    };
+                    body.extend_from_slice(&x.to_le_bytes());
+                }
+            }
+            DType::Bool => {
+                body.extend(
+                    col.values()
+                        .iter()
+                        .map(|val| matches!(val, ColumnVal::Two(true)) as u8),
+                );
+            }
+            DType::Str => write_strings(&mut body, col.values()),
+        }
+        let len = body.len() - offset;
+
+        let nulls = if col.values().iter().any(|val| val.is_null()) {
+            pad8(&mut body);
+            let off = body.len();
+            body.extend(col.values().iter().map(|val| !val.is_null() as u8));
+            off
+        } else {
+            0
+        };
+
+        let dtype_code: u8 = match col.dtype() {
+            DType::Str => 0,
+            DType::Bool => 1,
+            DType::Float => 2,
+            DType::Int => 3,
+        };
+        header.extend_from_slice(&(label.len() as u32).to_le_bytes());
+        header.extend_from_slice(label.as_bytes());
+        header.push(dtype_code);
+        header.extend_from_slice(&(offset as u64).to_le_bytes());
+        header.extend_from_slice(&(len as u64).to_le_bytes());
+        header.extend_from_slice(&(nulls as u64).to_le_bytes());
+    }
+
+    let header_len = header.len();
+    let mut contents = header;
+    pad8_to(&mut contents, PREAMBLE_LEN);
+    contents.extend_from_slice(&body);
+
+    let mut preamble = Vec::with_capacity(PREAMBLE_LEN);
+    preamble.extend_from_slice(MAGIC);
+    preamble.extend_from_slice(&VERSION.to_le_bytes());
+    preamble.extend_from_slice(&(df.ncols() as u32).to_le_bytes());
+    preamble.extend_from_slice(&(nrows as u64).to_le_bytes());
+    preamble.extend_from_slice(&(header_len as u32).to_le_bytes());
+    preamble.extend_from_slice(&crc32fast::hash(&contents).to_le_bytes());
+
+    // Write to a temporary file and rename it so a reader never maps a half-written cache.
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp)?;
+    file.write_all(&preamble)?;
+    file.write_all(&contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn write_strings(body: &mut Vec<u8>, values: &[ColumnVal]) {
+    let strings: Vec<String> = values
+        .iter()
+        .map(|val| match val {
+            ColumnVal::Null => String::new(),
+            other => other.to_string(),
+        })
+        .collect();
+    let mut end = 0u64;
+    body.extend_from_slice(&end.to_le_bytes());
+    for s in &strings {
+        end += s.len() as u64;
+        body.extend_from_slice(&end.to_le_bytes());
+    }
+    for s in &strings {
+        body.extend_from_slice(s.as_bytes());
+    }
+}
+
+// Reinterprets an 8-byte aligned block as i64/f64. Both types accept any bit pattern,
+// so the only things to check are alignment and byte order.
+fn cast_slice<T>(bytes: &[u8]) -> Option<&[T]> {
+    if cfg!(target_endian = "big") {
+        return None;
+    }
+    let (head, values, tail) = unsafe { bytes.align_to::<T>() };
+    if head.is_empty() && tail.is_empty() {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+// Str offsets must start at 0, never decrease and end at the number of string bytes.
+fn string_offsets_valid(data: &[u8], nrows: usize) -> bool {
+    let bytes = data.len() - (nrows + 1) * 8;
+    let mut previous = 0;
+    for i in 0..=nrows {
+        let offset = read_u64(data, i * 8) as usize;
+        if offset < previous || (i == 0 && offset != 0) {
+            return false;
+        }
+        previous = offset;
+    }
+    previous == bytes
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap())
+}
+
+fn align8(n: usize) -> usize {
+    n.div_ceil(8) * 8
+}
+
+fn pad8(buf: &mut Vec<u8>) {
+    buf.resize(align8(buf.len()), 0);
+}
+
+// Pads `buf` so that it ends on an 8-byte boundary once written after `prefix` bytes.
+fn pad8_to(buf: &mut Vec<u8>, prefix: usize) {
+    buf.resize(align8(prefix + buf.len()) - prefix, 0);
+}
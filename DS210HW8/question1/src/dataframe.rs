@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::thread;
+
+use crate::aggregate::{Aggregator, Median};
+use crate::cache::{self, MappedFrame};
+
+use crate::dtype::{ColumnVal, DType};
+use crate::error::Error;
+use crate::schema::Schema;
+use crate::series::Series;
+use crate::validation::Validator;
+
+#[derive(Debug, Clone, Default)]
+pub struct DataFrame {
+    columns: HashMap<String, Series>,
+    labels: Vec<String>,
+}
+
+impl DataFrame {
+    pub fn new() -> Self {
+        DataFrame {
+            columns: HashMap::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn read_csv(&mut self, path: &str, types: &[DType]) -> Result<(), Error> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b',')
+            .has_headers(true)
+            .from_path(path)?;
+
+        let headers = rdr.headers()?;
+        if headers.len() != types.len() {
+            return Err(Error::Types {
+                columns: headers.len(),
+                types: types.len(),
+            });
+        }
+        self.labels = headers.iter().map(|s| s.to_string()).collect();
+        self.columns.clear();
+        for (label, &dtype) in self.labels.iter().zip(types) {
+            self.columns
+                .insert(label.clone(), Series::with_dtype(label, dtype, Vec::new()));
+        }
+
+        for result in rdr.records() {
+            let r = result?;
+            let row = r.position().map_or(0, |p| p.line());
+
+            for (i, elem) in r.iter().enumerate() {
+                let label: &String = &self.labels[i];
+                let col = self.columns.get_mut(label).unwrap();
+                let val = col.dtype().parse(elem).ok_or_else(|| Error::Parse {
+                    row,
+                    column: label.clone(),
+                    value: elem.to_string(),
+                    dtype: col.dtype(),
+                })?;
+                col.push(val);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_csv_checked(
+        &mut self,
+        path: &str,
+        types: &[DType],
+        validator: &Validator,
+    ) -> Result<(), Error> {
+        let report = validator.validate(path, types)?;
+        if report.nrows() > 0 {
+            return Err(Error::Invalid(report));
+        }
+        self.read_csv(path, types)
+    }
+
+    pub fn write_cache(&self, path: &str) -> Result<(), Error> {
+        cache::write(self, path)
+    }
+
+    // Maps a cache written by `write_cache`. Nothing is copied until a column is
+    // asked for; call `to_dataframe` for an owned frame.
+    pub fn read_cache(path: &str) -> Result<MappedFrame, Error> {
+        MappedFrame::open(path)
+    }
+
+    // Maps the column cache when it is at least as new as the CSV and was written
+    // with the same types; otherwise parses the CSV, refreshes the cache and maps that.
+    pub fn read_csv_cached(
+        path: &str,
+        types: &[DType],
+        cache_path: &str,
+    ) -> Result<MappedFrame, Error> {
+        let csv_modified = fs::metadata(path)?.modified()?;
+        let fresh = fs::metadata(cache_path)
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| modified >= csv_modified);
+
+        if fresh {
+            if let Ok(mapped) = MappedFrame::open(cache_path) {
+                if mapped.schema().dtypes() == types {
+                    return Ok(mapped);
+                }
+            }
+        }
+
+        let mut df = DataFrame::new();
+        df.read_csv(path, types)?;
+        df.write_cache(cache_path)?;
+        MappedFrame::open(cache_path)
+    }
+
+    pub fn from_series(columns: Vec<Series>) -> Self {
+        let mut df = DataFrame::new();
+        for series in columns {
+            df.labels.push(series.name().to_string());
+            df.columns.insert(series.name().to_string(), series);
+        }
+        df
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn column(&self, label: &str) -> Option<&Series> {
+        self.columns.get(label)
+    }
+
+    pub fn schema(&self) -> Schema {
+        self.labels.iter().fold(Schema::new(), |schema, label| {
+            schema.field(label, self.columns[label].dtype())
+        })
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.labels
+            .first()
+            .and_then(|label| self.columns.get(label))
+            .map_or(0, |col| col.len())
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn print(&self) {
+        let mut col_widths: Vec<usize> = self.labels.iter().map(|label| label.len()).collect();
+
+        for (i, label) in self.labels.iter().enumerate() {
+            if let Some(col) = self.columns.get(label) {
+                for val in col.values() {
+                    let val_len = match val {
+                        ColumnVal::One(s) => s.len(),
+                        ColumnVal::Two(b) => b.to_string().len(),
+                        ColumnVal::Three(f) => format!("{:.1}", f).len(),
+                        ColumnVal::Four(i) => i.to_string().len(),
+                        ColumnVal::Null => 4,
+                    };
+                    col_widths[i] = col_widths[i].max(val_len);
+                }
+            }
+        }
+
+        for (i, label) in self.labels.iter().enumerate() {
+            print!("{:<width$} ", label, width = col_widths[i]);
+        }
+        println!();
+
+        for i in 0..self.nrows() {
+            for (j, label) in self.labels.iter().enumerate() {
+                if let Some(val) = self.columns.get(label).and_then(|col| col.get(i)) {
+                    match val {
+                        ColumnVal::One(s) => print!("{:<width$} ", s, width = col_widths[j]),
+                        ColumnVal::Two(b) => print!("{:<width$} ", b, width = col_widths[j]),
+                        ColumnVal::Three(f) => {
+                            print!("{:<width$.1} ", f, width = col_widths[j])
+                        }
+                        ColumnVal::Four(i) => print!("{:<width$} ", i, width = col_widths[j]),
+                        ColumnVal::Null => print!("{:<width$} ", "null", width = col_widths[j]),
+                    }
+                }
+            }
+            println!();
+        }
+    }
+
+    pub fn add_column(&self, label: String, data: Vec<ColumnVal>) -> DataFrame {
+        let mut new_df = DataFrame::new();
+        new_df.labels = self.labels.clone();
+        new_df.columns = self.columns.clone();
+
+        new_df.labels.push(label.clone());
+        new_df
+            .columns
+            .insert(label.clone(), Series::new(&label, data));
+
+        new_df
+    }
+
+    // Uses the values of `label` as the row labels of every column, so that Series
+    // arithmetic between frames pairs rows by label instead of by position.
+    pub fn set_index(&self, label: &str) -> DataFrame {
+        let index: Vec<String> = self.columns[label]
+            .values()
+            .iter()
+            .map(|val| val.to_string())
+            .collect();
+        let mut new_df = self.clone();
+        for col in new_df.columns.values_mut() {
+            col.set_index(index.clone());
+        }
+        new_df
+    }
+
+    pub fn index(&self) -> Option<&[String]> {
+        self.labels
+            .first()
+            .and_then(|label| self.columns.get(label))
+            .and_then(|col| col.index())
+    }
+
+    // Adds a series as a new column, lining its values up with this frame's index.
+    pub fn add_series(&self, series: &Series) -> DataFrame {
+        let mut col = match (self.index(), series.index()) {
+            (Some(index), Some(_)) => series.reindex(index),
+            _ => series.clone(),
+        };
+        if self.index().is_none() {
+            col.reset_index();
+        }
+
+        let mut new_df = self.clone();
+        new_df.labels.push(series.name().to_string());
+        new_df.columns.insert(series.name().to_string(), col);
+        new_df
+    }
+
+    pub fn merge_frame(&self, other: &DataFrame) -> DataFrame {
+        let mut new_df = DataFrame::new();
+        new_df.labels = self.labels.clone();
+
+        for label in &self.labels {
+            let mut combined = self.columns[label].clone();
+            combined.extend(&other.columns[label]);
+            new_df.columns.insert(label.clone(), combined);
+        }
+        new_df
+    }
+
+    pub fn find_columns(&self, labels: &[String]) -> DataFrame {
+        let mut new_df = DataFrame::new();
+        for label in labels {
+            new_df.labels.push(label.clone());
+            new_df
+                .columns
+                .insert(label.clone(), self.columns[label].clone());
+        }
+        new_df
+    }
+
+    pub fn restrict_columns(&self, labels: &[String]) -> DataFrame {
+        let mut new_df = DataFrame::new();
+        new_df.labels = labels.to_vec();
+
+        for label in labels {
+            new_df
+                .columns
+                .insert(label.clone(), self.columns[label].clone());
+        }
+        new_df
+    }
+
+    pub fn filter(&self, label: &str, operation: fn(&ColumnVal) -> bool) -> DataFrame {
+        let mut new_df = DataFrame::new();
+        new_df.labels = self.labels.clone();
+
+        let indices: Vec<usize> = self.columns[label]
+            .values()
+            .iter()
+            .enumerate()
+            .filter(|(_, val)| operation(val))
+            .map(|(i, _)| i)
+            .collect();
+
+        for label in &self.labels {
+            new_df
+                .columns
+                .insert(label.clone(), self.columns[label].take(&indices));
+        }
+
+        new_df
+    }
+
+    pub fn column_op<A: Aggregator>(&self, labels: &[String], agg: &A) -> ColumnVal {
+        agg.finish(self.fold_rows(labels, agg, 0..self.nrows()))
+    }
+
+    // Splits the rows into `threads` contiguous chunks, folds each chunk on its own
+    // thread and merges the partial states in row order.
+    pub fn par_column_op<A>(&self, labels: &[String], agg: &A, threads: usize) -> ColumnVal
+    where
+        A: Aggregator + Sync,
+        A::State: Send,
+    {
+        let nrows = self.nrows();
+        let chunk = nrows.div_ceil(threads.max(1)).max(1);
+        let partials: Vec<A::State> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..nrows)
+                .step_by(chunk)
+                .map(|start| {
+                    let rows = start..(start + chunk).min(nrows);
+                    scope.spawn(move || self.fold_rows(labels, agg, rows))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut state = agg.init();
+        for partial in partials {
+            agg.merge(&mut state, partial);
+        }
+        agg.finish(state)
+    }
+
+    // Returns one row per distinct value of `key` (in order of first appearance) with
+    // the aggregate of `labels` over that group's rows in a column named `output`.
+    pub fn group_by<A: Aggregator>(
+        &self,
+        key: &str,
+        labels: &[String],
+        agg: &A,
+        output: &str,
+    ) -> DataFrame {
+        let key_col = &self.columns[key];
+        let columns: Vec<&Series> = labels.iter().map(|label| &self.columns[label]).collect();
+
+        let mut groups: HashMap<String, usize> = HashMap::new();
+        let mut keys: Vec<ColumnVal> = Vec::new();
+        let mut states: Vec<A::State> = Vec::new();
+        for (i, key_val) in key_col.values().iter().enumerate() {
+            let g = *groups.entry(key_val.to_string()).or_insert_with(|| {
+                keys.push(key_val.clone());
+                states.push(agg.init());
+                states.len() - 1
+            });
+            let row: Vec<&ColumnVal> = columns.iter().map(|col| &col.values()[i]).collect();
+            agg.update(&mut states[g], &row);
+        }
+
+        let results = states.into_iter().map(|state| agg.finish(state)).collect();
+        DataFrame::from_series(vec![
+            Series::with_dtype(key, key_col.dtype(), keys),
+            Series::new(output, results),
+        ])
+    }
+
+    pub(crate) fn fold_rows<A: Aggregator>(
+        &self,
+        labels: &[String],
+        agg: &A,
+        rows: Range<usize>,
+    ) -> A::State {
+        let columns: Vec<&Series> = labels.iter().map(|label| &self.columns[label]).collect();
+        let mut state = agg.init();
+        let mut row: Vec<&ColumnVal> = Vec::with_capacity(columns.len());
+        for i in rows {
+            row.clear();
+            row.extend(columns.iter().map(|col| &col.values()[i]));
+            agg.update(&mut state, &row);
+        }
+        state
+    }
+
+    pub fn median(&self, label: &str) -> f64 {
+        if let ColumnVal::Three(median) = self.column_op(&[label.to_string()], &Median) {
+            median
+        } else {
+            0.0
+        }
+    }
+
+    pub fn sub_columns(&self, label1: &str, label2: &str) -> Series {
+        let mut differences = &self.columns[label1] - &self.columns[label2];
+        differences.rename(&format!("{}-{}", label1, label2));
+        differences
+    }
+}
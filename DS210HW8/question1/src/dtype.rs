@@ -0,0 +1,65 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnVal {
+    One(String),
+    Two(bool),
+    Three(f64),
+    Four(i64),
+    Null,
+}
+
+impl ColumnVal {
+    pub fn dtype(&self) -> Option<DType> {
+        match self {
+            ColumnVal::One(_) => Some(DType::Str),
+            ColumnVal::Two(_) => Some(DType::Bool),
+            ColumnVal::Three(_) => Some(DType::Float),
+            ColumnVal::Four(_) => Some(DType::Int),
+            ColumnVal::Null => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ColumnVal::Null)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ColumnVal::Three(f) => Some(*f),
+            ColumnVal::Four(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ColumnVal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnVal::One(s) => write!(f, "{}", s),
+            ColumnVal::Two(b) => write!(f, "{}", b),
+            ColumnVal::Three(x) => write!(f, "{}", x),
+            ColumnVal::Four(i) => write!(f, "{}", i),
+            ColumnVal::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DType {
+    Str,
+    Bool,
+    Float,
+    Int,
+}
+
+impl DType {
+    pub fn parse(&self, elem: &str) -> Option<ColumnVal> {
+        match self {
+            DType::Str => Some(ColumnVal::One(elem.to_string())),
+            DType::Bool => Some(ColumnVal::Two(elem.trim().to_lowercase() == "true")),
+            DType::Float => elem.trim().parse::<f64>().ok().map(ColumnVal::Three),
+            DType::Int => elem.trim().parse::<i64>().ok().map(ColumnVal::Four),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{fmt, io};
+
+use crate::dataframe::DataFrame;
+use crate::dtype::DType;
+
+#[derive(Debug)]
+pub enum Error {
+    Csv(csv::Error),
+    Io(io::Error),
+    Cache(String),
+    // The type list does not have one entry per column of the header.
+    Types {
+        columns: usize,
+        types: usize,
+    },
+    Parse {
+        row: u64,
+        column: String,
+        value: String,
+        dtype: DType,
+    },
+    Invalid(DataFrame),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Csv(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Cache(msg) => write!(f, "{}", msg),
+            Error::Types { columns, types } => {
+                write!(f, "{} types given for {} columns", types, columns)
+            }
+            Error::Parse {
+                row,
+                column,
+                value,
+                dtype,
+            } => write!(
+                f,
+                "row {}: could not read {:?} in column {} as {:?}",
+                row, value, column, dtype
+            ),
+            Error::Invalid(report) => write!(f, "{} validation errors", report.nrows()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,17 @@
+pub mod aggregate;
+mod cache;
+mod dataframe;
+mod dtype;
+mod error;
+mod schema;
+mod series;
+pub mod validation;
+
+pub use aggregate::{Aggregator, StreamingAgg};
+pub use cache::MappedFrame;
+pub use dataframe::DataFrame;
+pub use dtype::{ColumnVal, DType};
+pub use error::Error;
+pub use schema::Schema;
+pub use series::Series;
+pub use validation::{Constraint, Validator};
@@ -0,0 +1,44 @@
+use crate::dtype::DType;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    fields: Vec<(String, DType)>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema { fields: Vec::new() }
+    }
+
+    pub fn field(mut self, name: &str, dtype: DType) -> Self {
+        self.fields.push((name.to_string(), dtype));
+        self
+    }
+
+    pub fn fields(&self) -> &[(String, DType)] {
+        &self.fields
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.fields.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    pub fn dtypes(&self) -> Vec<DType> {
+        self.fields.iter().map(|(_, dtype)| *dtype).collect()
+    }
+
+    pub fn dtype(&self, name: &str) -> Option<DType> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, dtype)| *dtype)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::dtype::{ColumnVal, DType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    name: String,
+    dtype: DType,
+    values: Vec<ColumnVal>,
+    index: Option<Vec<String>>,
+}
+
+impl Series {
+    // The dtype is taken from the first non-null value; an all-null or empty series
+    // defaults to strings.
+    pub fn new(name: &str, values: Vec<ColumnVal>) -> Self {
+        let dtype = values
+            .iter()
+            .find_map(|val| val.dtype())
+            .unwrap_or(DType::Str);
+        Series::with_dtype(name, dtype, values)
+    }
+
+    pub fn with_dtype(name: &str, dtype: DType, values: Vec<ColumnVal>) -> Self {
+        Series {
+            name: name.to_string(),
+            dtype,
+            values,
+            index: None,
+        }
+    }
+
+    pub fn with_index(mut self, index: Vec<String>) -> Self {
+        self.set_index(index);
+        self
+    }
+
+    pub fn set_index(&mut self, index: Vec<String>) {
+        assert_eq!(
+            index.len(),
+            self.values.len(),
+            "index length does not match series {}",
+            self.name
+        );
+        self.index = Some(index);
+    }
+
+    pub fn reset_index(&mut self) {
+        self.index = None;
+    }
+
+    pub fn index(&self) -> Option<&[String]> {
+        self.index.as_deref()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    pub fn values(&self) -> &[ColumnVal] {
+        &self.values
+    }
+
+    pub fn get(&self, i: usize) -> Option<&ColumnVal> {
+        self.values.get(i)
+    }
+
+    // Looks a value up by index label. Without an index, labels are row positions.
+    pub fn get_label(&self, label: &str) -> Option<&ColumnVal> {
+        match &self.index {
+            Some(index) => index
+                .iter()
+                .position(|l| l == label)
+                .map(|i| &self.values[i]),
+            None => label.parse::<usize>().ok().and_then(|i| self.values.get(i)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Appends to a series without an index; indexed series need `push_labeled`.
+    pub fn push(&mut self, val: ColumnVal) {
+        assert!(
+            self.index.is_none(),
+            "series {} has an index, so new values need a label",
+            self.name
+        );
+        self.values.push(val);
+    }
+
+    // Appends to an indexed series under a label it does not have yet.
+    pub fn push_labeled(&mut self, label: &str, val: ColumnVal) {
+        let index = self
+            .index
+            .as_mut()
+            .unwrap_or_else(|| panic!("series {} has no index", self.name));
+        assert!(
+            !index.iter().any(|l| l == label),
+            "label {} is already in series {}",
+            label,
+            self.name
+        );
+        index.push(label.to_string());
+        self.values.push(val);
+    }
+
+    pub fn extend(&mut self, other: &Series) {
+        self.values.extend(other.values.iter().cloned());
+        self.index = match (self.index.take(), &other.index) {
+            (Some(mut index), Some(more)) => {
+                index.extend(more.iter().cloned());
+                Some(index)
+            }
+            _ => None,
+        };
+    }
+
+    pub fn take(&self, indices: &[usize]) -> Series {
+        let values = indices.iter().map(|&i| self.values[i].clone()).collect();
+        let mut series = Series::with_dtype(&self.name, self.dtype, values);
+        if let Some(index) = &self.index {
+            series.index = Some(indices.iter().map(|&i| index[i].clone()).collect());
+        }
+        series
+    }
+
+    // Returns the values laid out in the order of `labels`, with null for labels this
+    // series does not have. If a label appears more than once, the first match is used.
+    pub fn reindex(&self, labels: &[String]) -> Series {
+        let positions = self.positions();
+        let values = labels
+            .iter()
+            .map(|label| match positions.get(label) {
+                Some(&i) => self.values[i].clone(),
+                None => ColumnVal::Null,
+            })
+            .collect();
+        Series::with_dtype(&self.name, self.dtype, values).with_index(labels.to_vec())
+    }
+
+    fn labels(&self) -> Vec<String> {
+        match &self.index {
+            Some(index) => index.clone(),
+            None => (0..self.values.len()).map(|i| i.to_string()).collect(),
+        }
+    }
+
+    fn positions(&self) -> HashMap<String, usize> {
+        let mut positions = HashMap::new();
+        for (i, label) in self.labels().into_iter().enumerate() {
+            positions.entry(label).or_insert(i);
+        }
+        positions
+    }
+
+    // Pairs up values by index label (our labels first, then any new ones from
+    // `other`) and applies the operation to each pair. Labels missing on either side, nulls
+    // and non-numeric values produce null.
+    fn zip_with(
+        &self,
+        other: &Series,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> Series {
+        let mut labels = self.labels();
+        let mut seen: HashSet<String> = labels.iter().cloned().collect();
+        for label in other.labels() {
+            if seen.insert(label.clone()) {
+                labels.push(label);
+            }
+        }
+
+        let left = self.reindex(&labels);
+        let right = other.reindex(&labels);
+        let values: Vec<ColumnVal> = left
+            .values
+            .iter()
+            .zip(&right.values)
+            .map(|pair| match pair {
+                (ColumnVal::Four(a), ColumnVal::Four(b)) => {
+                    int_op(*a, *b).map_or(ColumnVal::Null, ColumnVal::Four)
+                }
+                (a, b) => match (a.as_f64(), b.as_f64()) {
+                    (Some(a), Some(b)) => ColumnVal::Three(float_op(a, b)),
+                    _ => ColumnVal::Null,
+                },
+            })
+            .collect();
+
+        let dtype = if self.dtype == DType::Int && other.dtype == DType::Int {
+            DType::Int
+        } else {
+            DType::Float
+        };
+        let mut series = Series::with_dtype(&self.name, dtype, values);
+        if self.index.is_some() || other.index.is_some() {
+            series.index = Some(labels);
+        }
+        series
+    }
+}
+
+impl Add for &Series {
+    type Output = Series;
+
+    fn add(self, other: &Series) -> Series {
+        self.zip_with(other, i64::checked_add, |a, b| a + b)
+    }
+}
+
+impl Sub for &Series {
+    type Output = Series;
+
+    fn sub(self, other: &Series) -> Series {
+        self.zip_with(other, i64::checked_sub, |a, b| a - b)
+    }
+}
+
+impl Mul for &Series {
+    type Output = Series;
+
+    fn mul(self, other: &Series) -> Series {
+        self.zip_with(other, i64::checked_mul, |a, b| a * b)
+    }
+}
+
+impl Div for &Series {
+    type Output = Series;
+
+    fn div(self, other: &Series) -> Series {
+        self.zip_with(other, i64::checked_div, |a, b| a / b)
+    }
+}
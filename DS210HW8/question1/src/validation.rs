@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::dataframe::DataFrame;
+use crate::dtype::{ColumnVal, DType};
+use crate::error::Error;
+use crate::series::Series;
+
+#[derive(Debug, Clone)]
+pub enum Constraint {
+    NonNull,
+    Range(f64, f64),
+    Pattern(Regex),
+    Unique,
+    OneOf(Vec<String>),
+}
+
+impl Constraint {
+    fn name(&self) -> &'static str {
+        match self {
+            Constraint::NonNull => "non_null",
+            Constraint::Range(_, _) => "range",
+            Constraint::Pattern(_) => "pattern",
+            Constraint::Unique => "unique",
+            Constraint::OneOf(_) => "one_of",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Validator {
+    rules: Vec<(String, Constraint)>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Validator { rules: Vec::new() }
+    }
+
+    pub fn rule(mut self, label: &str, constraint: Constraint) -> Self {
+        self.rules.push((label.to_string(), constraint));
+        self
+    }
+
+    // Checks every raw record of the file against the declared types and rules and
+    // returns a report frame with one row per violation (empty when the file is clean).
+    // Rows are numbered by their line in the file, so the header is line 1.
+    pub fn validate(&self, path: &str, types: &[DType]) -> Result<DataFrame, Error> {
+        let mut report = Report::new();
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b',')
+            .has_headers(true)
+            .flexible(true)
+            .from_path(path)?;
+
+        let labels: Vec<String> = rdr.headers()?.iter().map(|s| s.to_string()).collect();
+
+        if labels.len() != types.len() {
+            report.push(
+                1,
+                "*",
+                "types",
+                &format!(
+                    "{} types declared for {} columns",
+                    types.len(),
+                    labels.len()
+                ),
+            );
+        }
+
+        for (label, _) in &self.rules {
+            if !labels.contains(label) {
+                report.push(1, label, "missing_column", "");
+            }
+        }
+
+        let mut seen: HashSet<(usize, String)> = HashSet::new();
+
+        for result in rdr.records() {
+            let r = result?;
+            let row = r.position().map(|p| p.line()).unwrap_or(0);
+
+            if r.len() != labels.len() {
+                report.push(
+                    row,
+                    "*",
+                    "ragged",
+                    &format!("expected {} fields, found {}", labels.len(), r.len()),
+                );
+                continue;
+            }
+
+            for (i, elem) in r.iter().enumerate() {
+                let label = &labels[i];
+                let value = elem.trim();
+                let rules: Vec<&Constraint> = self
+                    .rules
+                    .iter()
+                    .filter(|(l, _)| l == label)
+                    .map(|(_, c)| c)
+                    .collect();
+
+                if value.is_empty() {
+                    if rules.iter().any(|c| matches!(c, Constraint::NonNull)) {
+                        report.push(row, label, "non_null", elem);
+                    } else if matches!(types.get(i), Some(DType::Float) | Some(DType::Int)) {
+                        report.push(row, label, "type", elem);
+                    }
+                    continue;
+                }
+
+                let parses = match types.get(i) {
+                    Some(DType::Bool) => {
+                        matches!(value.to_lowercase().as_str(), "true" | "false")
+                    }
+                    Some(dtype) => dtype.parse(value).is_some(),
+                    None => true,
+                };
+                if !parses {
+                    report.push(row, label, "type", elem);
+                    continue;
+                }
+
+                for constraint in rules {
+                    let ok = match constraint {
+                        Constraint::NonNull => true,
+                        Constraint::Range(min, max) => match value.parse::<f64>() {
+                            Ok(x) => x >= *min && x <= *max,
+                            Err(_) => false,
+                        },
+                        Constraint::Pattern(re) => re.is_match(value),
+                        Constraint::Unique => seen.insert((i, value.to_string())),
+                        Constraint::OneOf(allowed) => allowed.iter().any(|a| a == value),
+                    };
+                    if !ok {
+                        report.push(row, label, constraint.name(), elem);
+                    }
+                }
+            }
+        }
+
+        Ok(report.finish())
+    }
+}
+
+struct Report {
+    rows: Vec<ColumnVal>,
+    columns: Vec<ColumnVal>,
+    rules: Vec<ColumnVal>,
+    values: Vec<ColumnVal>,
+}
+
+impl Report {
+    fn new() -> Self {
+        Report {
+            rows: Vec::new(),
+            columns: Vec::new(),
+            rules: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: u64, label: &str, rule: &str, value: &str) {
+        self.rows.push(ColumnVal::Four(row as i64));
+        self.columns.push(ColumnVal::One(label.to_string()));
+        self.rules.push(ColumnVal::One(rule.to_string()));
+        self.values.push(ColumnVal::One(value.to_string()));
+    }
+
+    fn finish(self) -> DataFrame {
+        DataFrame::from_series(vec![
+            Series::with_dtype("Row", DType::Int, self.rows),
+            Series::with_dtype("Column", DType::Str, self.columns),
+            Series::with_dtype("Rule", DType::Str, self.rules),
+            Series::with_dtype("Value", DType::Str, self.values),
+        ])
+    }
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use common::players;
+use dataframe::aggregate::{Count, Mean, Median, Sum};
+use dataframe::{Aggregator, ColumnVal, DataFrame, Series, StreamingAgg};
+
+fn labels(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+// Counts values above a threshold chosen at runtime.
+struct CountAbove {
+    threshold: f64,
+}
+
+impl Aggregator for CountAbove {
+    type State = i64;
+
+    fn init(&self) -> i64 {
+        0
+    }
+
+    fn update(&self, state: &mut i64, row: &[&ColumnVal]) {
+        if row[0].as_f64().is_some_and(|x| x > self.threshold) {
+            *state += 1;
+        }
+    }
+
+    fn merge(&self, state: &mut i64, other: i64) {
+        *state += other;
+    }
+
+    fn finish(&self, state: i64) -> ColumnVal {
+        ColumnVal::Four(state)
+    }
+}
+
+// Mean of the first column weighted by the second.
+struct WeightedMean;
+
+impl Aggregator for WeightedMean {
+    type State = (f64, f64);
+
+    fn init(&self) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn update(&self, state: &mut (f64, f64), row: &[&ColumnVal]) {
+        if let (Some(x), Some(w)) = (row[0].as_f64(), row[1].as_f64()) {
+            state.0 += x * w;
+            state.1 += w;
+        }
+    }
+
+    fn merge(&self, state: &mut (f64, f64), other: (f64, f64)) {
+        state.0 += other.0;
+        state.1 += other.1;
+    }
+
+    fn finish(&self, state: (f64, f64)) -> ColumnVal {
+        ColumnVal::Three(state.0 / state.1)
+    }
+}
+
+#[test]
+fn test_builtin_aggregators() {
+    let df = players();
+    let ppg = labels(&["PPG"]);
+
+    assert_eq!(df.column_op(&ppg, &Count), ColumnVal::Four(4));
+    assert_eq!(df.column_op(&ppg, &Sum), ColumnVal::Three(69.0));
+    assert_eq!(df.column_op(&ppg, &Mean), ColumnVal::Three(17.25));
+    assert_eq!(df.column_op(&ppg, &Median), ColumnVal::Three(17.0));
+    assert_eq!(df.median("PPG"), 17.0);
+}
+
+#[test]
+fn test_median_skips_nan() {
+    let df = DataFrame::from_series(vec![Series::new(
+        "x",
+        vec![
+            ColumnVal::Three(3.0),
+            ColumnVal::Three(f64::NAN),
+            ColumnVal::Three(1.0),
+        ],
+    )]);
+    assert_eq!(
+        df.column_op(&labels(&["x"]), &Median),
+        ColumnVal::Three(2.0)
+    );
+}
+
+#[test]
+fn test_custom_aggregators() {
+    let df = players();
+
+    let above = CountAbove { threshold: 20.0 };
+    assert_eq!(df.column_op(&labels(&["PPG"]), &above), ColumnVal::Four(2));
+
+    let weighted = df.column_op(&labels(&["PPG", "Number"]), &WeightedMean);
+    let expected = (12.5 * 7.0 + 27.0 * 23.0 + 21.5 * 3.0 + 8.0 * 11.0) / 44.0;
+    assert_eq!(weighted, ColumnVal::Three(expected));
+}
+
+#[test]
+fn test_parallel_matches_sequential() {
+    let values: Vec<ColumnVal> = (0..1001).map(|i| ColumnVal::Four(i % 37)).collect();
+    let df = DataFrame::from_series(vec![Series::new("x", values)]);
+    let x = labels(&["x"]);
+
+    for threads in [1, 3, 8] {
+        assert_eq!(
+            df.par_column_op(&x, &Median, threads),
+            df.column_op(&x, &Median)
+        );
+        assert_eq!(df.par_column_op(&x, &Sum, threads), df.column_op(&x, &Sum));
+    }
+}
+
+#[test]
+fn test_group_by() {
+    let grouped = players().group_by("LikesPizza", &labels(&["PPG"]), &Mean, "MeanPPG");
+
+    assert_eq!(grouped.labels(), &labels(&["LikesPizza", "MeanPPG"]));
+    assert_eq!(
+        grouped.column("LikesPizza").unwrap().values(),
+        &[ColumnVal::Two(true), ColumnVal::Two(false)]
+    );
+    assert_eq!(
+        grouped.column("MeanPPG").unwrap().values(),
+        &[ColumnVal::Three(17.0), ColumnVal::Three(17.5)]
+    );
+}
+
+#[test]
+fn test_streaming_over_batches() {
+    let df = players();
+    let ppg = labels(&["PPG"]);
+
+    let mut stream = StreamingAgg::new(&Median, &ppg);
+    stream.feed(&df);
+    stream.feed(&df.filter("PPG", |val| matches!(val, ColumnVal::Three(x) if *x > 20.0)));
+
+    // 12.5, 27.0, 21.5, 8.0 plus 27.0, 21.5 again
+    assert_eq!(stream.finish(), ColumnVal::Three(21.5));
+}
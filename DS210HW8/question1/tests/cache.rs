@@ -0,0 +1,124 @@
+mod common;
+
+use std::fs;
+
+use common::{players, write_csv, PLAYERS, TYPES};
+use dataframe::{ColumnVal, DType, DataFrame, Error, MappedFrame, Series};
+
+fn cache_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("hw8_{}_{}.cols", name, std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_cache_round_trip() {
+    let df = players();
+    let path = cache_path("round_trip");
+    df.write_cache(&path).unwrap();
+
+    let loaded = DataFrame::read_cache(&path).unwrap().to_dataframe();
+    assert_eq!(loaded.schema(), df.schema());
+    for label in df.labels() {
+        assert_eq!(loaded.column(label), df.column(label));
+    }
+}
+
+#[test]
+fn test_cache_numeric_columns_are_borrowed() {
+    let path = cache_path("zero_copy");
+    players().write_cache(&path).unwrap();
+
+    let mapped = MappedFrame::open(&path).unwrap();
+    assert_eq!(mapped.nrows(), 4);
+    assert_eq!(
+        mapped.float_column("PPG").unwrap(),
+        &[12.5, 27.0, 21.5, 8.0]
+    );
+    assert_eq!(mapped.int_column("Number").unwrap(), &[7, 23, 3, 11]);
+    assert!(mapped.int_column("PPG").is_none());
+    assert!(mapped.validity("PPG").is_none());
+    assert_eq!(mapped.bool_column("LikesPizza").unwrap(), &[1, 0, 1, 0]);
+    assert_eq!(mapped.str_value("Name", 1), Some("Bob"));
+    assert_eq!(mapped.str_value("Name", 4), None);
+}
+
+#[test]
+fn test_cache_keeps_nulls() {
+    let df = DataFrame::from_series(vec![
+        Series::new("x", vec![ColumnVal::Four(1), ColumnVal::Null]),
+        Series::new("s", vec![ColumnVal::Null, ColumnVal::One("b".to_string())]),
+    ]);
+    let path = cache_path("nulls");
+    df.write_cache(&path).unwrap();
+
+    let mapped = MappedFrame::open(&path).unwrap();
+    assert_eq!(mapped.validity("x").unwrap(), &[1, 0]);
+    assert_eq!(
+        mapped.column("x").unwrap().values(),
+        df.column("x").unwrap().values()
+    );
+    assert_eq!(
+        mapped.column("s").unwrap().values(),
+        df.column("s").unwrap().values()
+    );
+    assert_eq!(mapped.schema().dtype("s"), Some(DType::Str));
+}
+
+#[test]
+fn test_cache_detects_corruption() {
+    let path = cache_path("corrupt");
+    players().write_cache(&path).unwrap();
+
+    let mut bytes = fs::read(&path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    fs::write(&path, &bytes).unwrap();
+
+    assert!(matches!(MappedFrame::open(&path), Err(Error::Cache(_))));
+}
+
+#[test]
+fn test_cache_rejects_bad_string_offsets() {
+    let path = cache_path("offsets");
+    players().write_cache(&path).unwrap();
+
+    // Point the end of the first Name string past the column, then fix up the
+    // checksum so only the offset check can catch it.
+    let mut bytes = fs::read(&path).unwrap();
+    let header_len = u32::from_le_bytes(bytes[24..28].try_into().unwrap()) as usize;
+    let name_column = (32 + header_len).div_ceil(8) * 8;
+    bytes[name_column + 8..name_column + 16].copy_from_slice(&1000u64.to_le_bytes());
+    let checksum = crc32fast::hash(&bytes[32..]);
+    bytes[28..32].copy_from_slice(&checksum.to_le_bytes());
+    fs::write(&path, &bytes).unwrap();
+
+    match MappedFrame::open(&path) {
+        Err(Error::Cache(msg)) => assert!(msg.ends_with("column Name has bad string offsets")),
+        other => panic!("expected a cache error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_read_csv_cached_writes_then_reuses_cache() {
+    let csv = write_csv("cached", PLAYERS);
+    let path = cache_path("cached");
+    let _ = fs::remove_file(&path);
+
+    let first = DataFrame::read_csv_cached(&csv, &TYPES, &path).unwrap();
+    assert!(fs::metadata(&path).is_ok());
+
+    let second = DataFrame::read_csv_cached(&csv, &TYPES, &path).unwrap();
+    assert_eq!(second.nrows(), 4);
+    assert_eq!(second.column("Name"), first.column("Name"));
+    assert_eq!(
+        second.float_column("PPG"),
+        Some(&[12.5, 27.0, 21.5, 8.0][..])
+    );
+
+    // A different set of types invalidates the cache instead of mis-reading it.
+    let as_strings = DataFrame::read_csv_cached(&csv, &[DType::Str; 6], &path).unwrap();
+    assert_eq!(as_strings.column("PPG").unwrap().dtype(), DType::Str);
+}
@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use dataframe::{ColumnVal, DType, DataFrame};
+
+pub const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/data.csv");
+
+// Name, Number, PPG, YearBorn, TotalPoints, LikesPizza
+pub const TYPES: [DType; 6] = [
+    DType::Str,
+    DType::Int,
+    DType::Float,
+    DType::Int,
+    DType::Int,
+    DType::Bool,
+];
+
+pub const PLAYERS: &str = "Name,Number,PPG,YearBorn,TotalPoints,LikesPizza
+Ann,7,12.5,1990,8000,true
+Bob,23,27.0,1985,30000,false
+Cal,3,21.5,1995,15000,true
+Dee,11,8.0,2000,2000,false
+";
+
+pub fn write_csv(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("hw8_{}_{}.csv", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+pub fn players() -> DataFrame {
+    let mut df = DataFrame::new();
+    df.read_csv(&write_csv("players", PLAYERS), &TYPES).unwrap();
+    df
+}
+
+pub fn strings(df: &DataFrame, label: &str) -> Vec<String> {
+    df.column(label)
+        .unwrap()
+        .values()
+        .iter()
+        .map(|val| match val {
+            ColumnVal::One(s) => s.clone(),
+            other => panic!("expected a string, found {:?}", other),
+        })
+        .collect()
+}
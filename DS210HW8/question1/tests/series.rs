@@ -0,0 +1,109 @@
+mod common;
+
+use common::{players, write_csv, TYPES};
+use dataframe::{ColumnVal, DType, DataFrame, Series};
+
+fn ints(values: &[i64]) -> Vec<ColumnVal> {
+    values.iter().map(|&i| ColumnVal::Four(i)).collect()
+}
+
+fn labels(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_series_infers_dtype() {
+    let series = Series::new("x", vec![ColumnVal::Null, ColumnVal::Three(1.5)]);
+    assert_eq!(series.dtype(), DType::Float);
+    assert_eq!(series.len(), 2);
+    assert!(series.index().is_none());
+}
+
+#[test]
+fn test_positional_ops_without_index() {
+    let a = Series::new("a", ints(&[10, 20, 30]));
+    let b = Series::new("b", ints(&[1, 2]));
+
+    let diff = &a - &b;
+    assert_eq!(
+        diff.values(),
+        &[ColumnVal::Four(9), ColumnVal::Four(18), ColumnVal::Null]
+    );
+    assert!(diff.index().is_none());
+}
+
+#[test]
+fn test_ops_align_on_index_labels() {
+    let a = Series::new("points", ints(&[10, 20, 30])).with_index(labels(&["x", "y", "z"]));
+    let b = Series::new("points", ints(&[3, 1, 7])).with_index(labels(&["z", "x", "w"]));
+
+    let sum = &a + &b;
+    assert_eq!(sum.index().unwrap(), &labels(&["x", "y", "z", "w"]));
+    assert_eq!(
+        sum.values(),
+        &[
+            ColumnVal::Four(11),
+            ColumnVal::Null,
+            ColumnVal::Four(33),
+            ColumnVal::Null,
+        ]
+    );
+}
+
+#[test]
+fn test_push_labeled_extends_the_index() {
+    let mut series = Series::new("points", ints(&[10, 20])).with_index(labels(&["1", "0"]));
+    series.push_labeled("x", ColumnVal::Four(30));
+    assert_eq!(series.index().unwrap(), &labels(&["1", "0", "x"]));
+    assert_eq!(series.get_label("x"), Some(&ColumnVal::Four(30)));
+}
+
+#[test]
+#[should_panic(expected = "label 0 is already in series points")]
+fn test_push_labeled_rejects_duplicate_labels() {
+    let mut series = Series::new("points", ints(&[10, 20])).with_index(labels(&["1", "0"]));
+    series.push_labeled("0", ColumnVal::Four(30));
+}
+
+#[test]
+fn test_mixed_numeric_ops_promote_to_float() {
+    let a = Series::new("a", ints(&[3, 4]));
+    let b = Series::new("b", vec![ColumnVal::Three(0.5), ColumnVal::Three(2.0)]);
+
+    let product = &a * &b;
+    assert_eq!(product.dtype(), DType::Float);
+    assert_eq!(
+        product.values(),
+        &[ColumnVal::Three(1.5), ColumnVal::Three(8.0)]
+    );
+
+    let quotient = &a / &Series::new("z", ints(&[0, 2]));
+    assert_eq!(quotient.values(), &[ColumnVal::Null, ColumnVal::Four(2)]);
+}
+
+#[test]
+fn test_frames_with_different_row_order() {
+    let df1 = players().set_index("Name");
+    let shuffled = "Name,Number,PPG,YearBorn,TotalPoints,LikesPizza
+Dee,11,8.0,2000,2500,false
+Bob,23,27.0,1985,31000,false
+Ann,7,12.5,1990,8400,true
+";
+    let mut df2 = DataFrame::new();
+    df2.read_csv(&write_csv("shuffled", shuffled), &TYPES)
+        .unwrap();
+    let df2 = df2.set_index("Name");
+
+    let gained = df2.column("TotalPoints").unwrap() - df1.column("TotalPoints").unwrap();
+    assert_eq!(gained.get_label("Ann"), Some(&ColumnVal::Four(400)));
+    assert_eq!(gained.get_label("Bob"), Some(&ColumnVal::Four(1000)));
+    assert_eq!(gained.get_label("Dee"), Some(&ColumnVal::Four(500)));
+    assert_eq!(gained.get_label("Cal"), Some(&ColumnVal::Null));
+
+    let with_gain = df1.add_series(&gained);
+    assert_eq!(with_gain.nrows(), 4);
+    assert_eq!(
+        with_gain.column("TotalPoints").unwrap().index(),
+        with_gain.index()
+    );
+}
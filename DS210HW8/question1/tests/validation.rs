@@ -0,0 +1,93 @@
+mod common;
+
+use common::write_csv;
+use dataframe::{ColumnVal, Constraint, DType, DataFrame, Error, Validator};
+use regex::Regex;
+
+fn report_rules(report: &DataFrame) -> Vec<(i64, String, String)> {
+    (0..report.nrows())
+        .map(|i| {
+            match (
+                report.column("Row").unwrap().get(i),
+                report.column("Column").unwrap().get(i),
+                report.column("Rule").unwrap().get(i),
+            ) {
+                (
+                    Some(ColumnVal::Four(row)),
+                    Some(ColumnVal::One(col)),
+                    Some(ColumnVal::One(rule)),
+                ) => (*row, col.clone(), rule.clone()),
+                _ => panic!("unexpected report layout"),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_validate_clean_file() {
+    let path = write_csv("clean", "Name,PPG,Active\nA,10.5,true\nB,20.0,false\n");
+    let validator = Validator::new()
+        .rule("Name", Constraint::NonNull)
+        .rule("Name", Constraint::Unique)
+        .rule("PPG", Constraint::Range(0.0, 50.0));
+
+    let mut df = DataFrame::new();
+    df.read_csv_checked(&path, &[DType::Str, DType::Float, DType::Bool], &validator)
+        .unwrap();
+    assert_eq!(df.nrows(), 2);
+}
+
+#[test]
+fn test_validate_reports_ragged_rows() {
+    let path = write_csv(
+        "ragged",
+        "Name,PPG,Active\nA,10.5,true\nB,20.0\nC,1.0,true,extra\n",
+    );
+    let report = Validator::new()
+        .validate(&path, &[DType::Str, DType::Float, DType::Bool])
+        .unwrap();
+
+    assert_eq!(
+        report_rules(&report),
+        vec![
+            (3, "*".to_string(), "ragged".to_string()),
+            (4, "*".to_string(), "ragged".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_reports_rule_violations() {
+    let path = write_csv(
+        "rules",
+        "Name,PPG,Team\nA,10.5,LAL\n,60.0,LAL\nA,abc,CHI\nB,5.0,BOS\n",
+    );
+    let validator = Validator::new()
+        .rule("Name", Constraint::NonNull)
+        .rule("Name", Constraint::Unique)
+        .rule("Name", Constraint::Pattern(Regex::new("^[A-Z]$").unwrap()))
+        .rule("PPG", Constraint::Range(0.0, 50.0))
+        .rule(
+            "Team",
+            Constraint::OneOf(vec!["LAL".to_string(), "CHI".to_string()]),
+        );
+
+    let mut df = DataFrame::new();
+    let report =
+        match df.read_csv_checked(&path, &[DType::Str, DType::Float, DType::Str], &validator) {
+            Err(Error::Invalid(report)) => report,
+            other => panic!("expected a validation report, got {:?}", other),
+        };
+
+    assert_eq!(
+        report_rules(&report),
+        vec![
+            (3, "Name".to_string(), "non_null".to_string()),
+            (3, "PPG".to_string(), "range".to_string()),
+            (4, "Name".to_string(), "unique".to_string()),
+            (4, "PPG".to_string(), "type".to_string()),
+            (5, "Team".to_string(), "one_of".to_string()),
+        ]
+    );
+    assert_eq!(df.nrows(), 0);
+}
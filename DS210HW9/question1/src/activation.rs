@@ -0,0 +1,124 @@
+use ndarray::{Array2, Axis};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Activation {
+    Sigmoid,
+    Relu,
+    LeakyRelu(f32),
+    Tanh,
+    Softmax,
+    Identity,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Activation {
+    pub fn forward(&self, z: &Array2<f32>) -> Array2<f32> {
+        match self {
+            Activation::Sigmoid => z.mapv(sigmoid),
+            Activation::Relu => z.mapv(|x| x.max(0.0)),
+            Activation::LeakyRelu(slope) => z.mapv(|x| if x > 0.0 { x } else { slope * x }),
+            Activation::Tanh => z.mapv(f32::tanh),
+            Activation::Softmax => {
+                // Subtracting the row maximum keeps exp() from overflowing.
+                let mut out = z.clone();
+                for mut row in out.rows_mut() {
+                    let max = row.fold(f32::NEG_INFINITY, |m, &x| m.max(x));
+                    row.mapv_inplace(|x| (x - max).exp());
+                    let sum = row.sum();
+                    row /= sum;
+                }
+                out
+            }
+            Activation::Identity => z.clone(),
+        }
+    }
+
+    // Turns the gradient with respect to the layer's output `a = f(z)` into the
+    // gradient with respect to its pre-activation `z`.
+    pub fn backward(&self, z: &Array2<f32>, a: &Array2<f32>, grad: &Array2<f32>) -> Array2<f32> {
+        match self {
+            Activation::Softmax => {
+                // The softmax Jacobian couples a whole row: dz_i = a_i * (g_i - sum_j g_j a_j).
+                let dot = (grad * a).sum_axis(Axis(1)).insert_axis(Axis(1));
+                a * &(grad - &dot)
+            }
+            _ => grad * &z.mapv(|x| self.derivative(x)),
+        }
+    }
+
+    // Derivative of the element-wise activations at the pre-activation `x`.
+    fn derivative(&self, x: f32) -> f32 {
+        match self {
+            Activation::Sigmoid => {
+                let s = sigmoid(x);
+                s * (1.0 - s)
+            }
+            Activation::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::LeakyRelu(slope) => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    *slope
+                }
+            }
+            Activation::Tanh => 1.0 - x.tanh().powi(2),
+            Activation::Softmax | Activation::Identity => 1.0,
+        }
+    }
+}
+
+impl FromStr for Activation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sigmoid" => Ok(Activation::Sigmoid),
+            "relu" => Ok(Activation::Relu),
+            "leaky_relu" => Ok(Activation::LeakyRelu(0.01)),
+            "tanh" => Ok(Activation::Tanh),
+            "softmax" => Ok(Activation::Softmax),
+            "identity" => Ok(Activation::Identity),
+            _ => Err(format!("unknown activation {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_softmax_rows_sum_to_one() {
+        let z =
+            Array2::from_shape_vec((2, 3), vec![1.0, 2.0, 3.0, 1000.0, 1000.0, 1000.0]).unwrap();
+        let a = Activation::Softmax.forward(&z);
+
+        for row in a.rows() {
+            assert!((row.sum() - 1.0).abs() < 1e-6);
+        }
+        assert!((a[[1, 0]] - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_derivatives_use_pre_activation() {
+        let z = Array2::from_shape_vec((1, 3), vec![-2.0, 0.0, 3.0]).unwrap();
+        let ones = Array2::ones((1, 3));
+
+        let relu = Activation::Relu.backward(&z, &Activation::Relu.forward(&z), &ones);
+        assert_eq!(relu.row(0).to_vec(), vec![0.0, 0.0, 1.0]);
+
+        let sigmoid = Activation::Sigmoid.backward(&z, &Activation::Sigmoid.forward(&z), &ones);
+        assert!((sigmoid[[0, 1]] - 0.25).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,274 @@
+// Random distortions of training images, so every epoch sees slightly different
+// digits. Rows are reshaped to side x side images, moved by one combined random
+// warp (shift, rotation about the centre and elastic distortion), resampled with
+// bilinear interpolation, given Gaussian noise and flattened again. Pixels that
+// come from outside the image are black, like the MNIST background.
+
+use ndarray::{Array2, ArrayView2, Axis};
+use ndarray_rand::rand_distr::{Distribution, Normal, Uniform};
+use rand::{Rng, RngCore};
+use std::error::Error;
+
+use crate::data::IMAGE_SIDE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Augmentation {
+    pub side: usize,
+    // Largest shift along each axis, in pixels.
+    pub shift: f32,
+    // Largest rotation either way, in degrees.
+    pub rotation: f32,
+    // Elastic distortion: random per-pixel displacements smoothed with a Gaussian of
+    // width `elastic_sigma` and scaled by `elastic_alpha` (Simard et al., 2003).
+    pub elastic_alpha: f32,
+    pub elastic_sigma: f32,
+    // Standard deviation of the noise added to every pixel.
+    pub noise: f32,
+}
+
+impl Default for Augmentation {
+    // No distortion at all; turn on what you want with the builders.
+    fn default() -> Self {
+        Augmentation {
+            side: IMAGE_SIDE,
+            shift: 0.0,
+            rotation: 0.0,
+            elastic_alpha: 0.0,
+            elastic_sigma: 4.0,
+            noise: 0.0,
+        }
+    }
+}
+
+impl Augmentation {
+    pub fn with_shift(mut self, pixels: f32) -> Self {
+        self.shift = pixels;
+        self
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_elastic(mut self, alpha: f32, sigma: f32) -> Self {
+        assert!(sigma > 0.0, "elastic sigma must be positive");
+        self.elastic_alpha = alpha;
+        self.elastic_sigma = sigma;
+        self
+    }
+
+    pub fn with_noise(mut self, std: f32) -> Self {
+        self.noise = std;
+        self
+    }
+
+    // Whether rows of `features` pixels are the side x side images this warps, so
+    // a mismatch is caught before training rather than by `batch`.
+    pub fn check(&self, features: usize) -> Result<(), Box<dyn Error>> {
+        if features != self.side * self.side {
+            return Err(format!(
+                "augmentation needs {0}x{0} images, but the data has {1} features",
+                self.side, features
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    // Augments every row of a batch on its own.
+    pub fn batch(&self, rows: &Array2<f32>, rng: &mut dyn RngCore) -> Array2<f32> {
+        assert_eq!(
+            rows.ncols(),
+            self.side * self.side,
+            "rows must be {0}x{0} images",
+            self.side
+        );
+        let mut output = rows.clone();
+        for mut row in output.axis_iter_mut(Axis(0)) {
+            let image = row
+                .view()
+                .into_shape((self.side, self.side))
+                .unwrap()
+                .to_owned();
+            let augmented = self.image(image.view(), rng);
+            row.assign(&augmented.into_shape(self.side * self.side).unwrap());
+        }
+        output
+    }
+
+    // One random augmentation of a side x side image with pixels in 0-1.
+    pub fn image(&self, image: ArrayView2<f32>, rng: &mut dyn RngCore) -> Array2<f32> {
+        let mut uniform = |limit: f32| {
+            if limit > 0.0 {
+                rng.gen_range(-limit..=limit)
+            } else {
+                0.0
+            }
+        };
+        let angle = uniform(self.rotation).to_radians();
+        let shift = (uniform(self.shift), uniform(self.shift));
+        let displacement = (self.elastic_alpha > 0.0)
+            .then(|| (self.displacement_field(rng), self.displacement_field(rng)));
+
+        let mut output = warp(image, angle, shift, displacement.as_ref());
+        if self.noise > 0.0 {
+            let normal = Normal::new(0.0, self.noise).unwrap();
+            output.mapv_inplace(|p| (p + normal.sample(rng)).clamp(0.0, 1.0));
+        }
+        output
+    }
+
+    // Uniform noise in -1..1 blurred with a Gaussian and scaled by alpha.
+    fn displacement_field(&self, rng: &mut dyn RngCore) -> Array2<f32> {
+        let side = self.side;
+        let uniform = Uniform::new_inclusive(-1.0f32, 1.0);
+        let field = Array2::from_shape_fn((side, side), |_| uniform.sample(rng));
+        blur(&field, self.elastic_sigma) * self.elastic_alpha
+    }
+}
+
+// Separable Gaussian blur, treating everything outside the image as zero.
+fn blur(image: &Array2<f32>, sigma: f32) -> Array2<f32> {
+    let radius = (3.0 * sigma).ceil() as isize;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.iter().map(|k| k / total).collect();
+
+    let (height, width) = image.dim();
+    let pass = |input: &Array2<f32>, along_rows: bool| {
+        Array2::from_shape_fn((height, width), |(y, x)| {
+            let mut sum = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let offset = k as isize - radius;
+                let (sy, sx) = if along_rows {
+                    (y as isize, x as isize + offset)
+                } else {
+                    (y as isize + offset, x as isize)
+                };
+                if (0..height as isize).contains(&sy) && (0..width as isize).contains(&sx) {
+                    sum += weight * input[[sy as usize, sx as usize]];
+                }
+            }
+            sum
+        })
+    };
+    pass(&pass(image, true), false)
+}
+
+// Each output pixel p reads the input at c + R(-angle)(p - c) - shift + d(p), where
+// c is the centre and d the optional (dx, dy) displacement field.
+fn warp(
+    image: ArrayView2<f32>,
+    angle: f32,
+    shift: (f32, f32),
+    displacement: Option<&(Array2<f32>, Array2<f32>)>,
+) -> Array2<f32> {
+    let (height, width) = image.dim();
+    let (cy, cx) = ((height as f32 - 1.0) / 2.0, (width as f32 - 1.0) / 2.0);
+    let (sin, cos) = angle.sin_cos();
+    Array2::from_shape_fn((height, width), |(y, x)| {
+        let (dy, dx) = (y as f32 - cy, x as f32 - cx);
+        let mut sx = cx + cos * dx + sin * dy - shift.0;
+        let mut sy = cy - sin * dx + cos * dy - shift.1;
+        if let Some((field_x, field_y)) = displacement {
+            sx += field_x[[y, x]];
+            sy += field_y[[y, x]];
+        }
+        bilinear(&image, sy, sx)
+    })
+}
+
+fn bilinear(image: &ArrayView2<f32>, y: f32, x: f32) -> f32 {
+    let (height, width) = image.dim();
+    let pixel = |y: isize, x: isize| {
+        if (0..height as isize).contains(&y) && (0..width as isize).contains(&x) {
+            image[[y as usize, x as usize]]
+        } else {
+            0.0
+        }
+    };
+    let (y0, x0) = (y.floor(), x.floor());
+    let (fy, fx) = (y - y0, x - x0);
+    let (y0, x0) = (y0 as isize, x0 as isize);
+    let top = pixel(y0, x0) * (1.0 - fx) + pixel(y0, x0 + 1) * fx;
+    let bottom = pixel(y0 + 1, x0) * (1.0 - fx) + pixel(y0 + 1, x0 + 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn digit() -> Array2<f32> {
+        Array2::from_shape_fn((5, 5), |(y, x)| if x == 1 && y > 0 { 1.0 } else { 0.0 })
+    }
+
+    #[test]
+    fn test_warp_shifts_and_rotates() {
+        let image = digit();
+        assert_eq!(warp(image.view(), 0.0, (0.0, 0.0), None), image);
+
+        let shifted = warp(image.view(), 0.0, (2.0, -1.0), None);
+        for ((y, x), &p) in shifted.indexed_iter() {
+            let source = (y as isize + 1, x as isize - 2);
+            let expected = if (0..5).contains(&source.0) && (0..5).contains(&source.1) {
+                image[[source.0 as usize, source.1 as usize]]
+            } else {
+                0.0
+            };
+            assert_eq!(p, expected);
+        }
+
+        // A quarter turn about the centre pixel maps column 1 onto row 1.
+        let rotated = warp(image.view(), FRAC_PI_2, (0.0, 0.0), None);
+        for ((y, x), &p) in rotated.indexed_iter() {
+            assert!((p - image[[4 - x, y]]).abs() < 1e-5);
+        }
+        assert_eq!(rotated.row(1).sum(), 4.0);
+    }
+
+    #[test]
+    fn test_blur_keeps_a_constant_interior() {
+        let flat = Array2::from_elem((21, 21), 1.0);
+        let blurred = blur(&flat, 1.0);
+        assert!((blurred[[10, 10]] - 1.0).abs() < 1e-5);
+        assert!(blurred[[0, 0]] < 0.5);
+    }
+
+    #[test]
+    fn test_batch_is_seeded_and_in_range() {
+        let rows = Array2::from_shape_fn((3, 784), |(i, j)| ((i + j) % 9) as f32 / 8.0);
+        let augmentation = Augmentation::default()
+            .with_shift(2.0)
+            .with_rotation(10.0)
+            .with_elastic(8.0, 4.0)
+            .with_noise(0.1);
+
+        let first = augmentation.batch(&rows, &mut StdRng::seed_from_u64(3));
+        let second = augmentation.batch(&rows, &mut StdRng::seed_from_u64(3));
+        assert_eq!(first, second);
+        assert_ne!(first, rows);
+        assert_eq!(first.dim(), rows.dim());
+        assert!(first.iter().all(|&p| (0.0..=1.0).contains(&p)));
+
+        let unchanged = Augmentation::default().batch(&rows, &mut StdRng::seed_from_u64(3));
+        assert_eq!(unchanged, rows);
+    }
+
+    #[test]
+    fn test_check_needs_square_images_of_the_side() {
+        let augmentation = Augmentation::default().with_shift(2.0);
+        assert!(augmentation.check(784).is_ok());
+        let message = augmentation.check(196).unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "augmentation needs 28x28 images, but the data has 196 features"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use ndarray::{s, Array2};
+use neural_network::activation::Activation;
+use neural_network::data::load_examples;
+use neural_network::loss::Loss;
+use neural_network::network::NeuralNetwork;
+use std::time::Instant;
+use std::{env, error::Error, thread};
+
+// Measures training and inference throughput (examples per second) on MNIST for
+// a range of thread counts.
+//   cargo run --release --bin benchmark -- [--idx <dir>] [--rows <n>] [--threads 1,2,4]
+// Reads ../MNIST_CSV/mnist_train.csv unless --idx points at the IDX files. Training
+// runs one epoch over the first --rows examples (10000 by default) in batches of
+// 256, on a network with hidden layers of 512 and 256 (784-512-256-10 for MNIST).
+struct Options {
+    idx: Option<String>,
+    rows: usize,
+    threads: Vec<usize>,
+}
+
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut options = Options {
+        idx: None,
+        rows: 10000,
+        threads: (0..)
+            .map(|i| 1 << i)
+            .take_while(|&n| n < cores)
+            .chain([cores])
+            .collect(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--idx" => options.idx = Some(value()?),
+            "--rows" => options.rows = value()?.parse()?,
+            "--threads" => {
+                options.threads = value()?
+                    .split(',')
+                    .map(|n| n.parse())
+                    .collect::<Result<_, _>>()?
+            }
+            other => return Err(format!("unknown argument {}", other).into()),
+        }
+    }
+    Ok(options)
+}
+
+fn network(inputs: usize, classes: usize) -> NeuralNetwork {
+    NeuralNetwork::new(&[inputs, 512, 256, classes], 0.1)
+        .with_activations(&[Activation::Relu, Activation::Relu, Activation::Softmax])
+        .with_loss(Loss::SoftmaxCrossEntropy)
+        .with_seed(0)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options()?;
+    let (features, labels) = load_examples(options.idx.as_deref(), "train", None)?;
+    let rows = options.rows.min(features.nrows());
+    let features: Array2<f32> = features.slice(s![..rows, ..]).to_owned();
+    let labels: Array2<f32> = labels.slice(s![..rows, ..]).to_owned();
+    println!("{} examples", rows);
+    println!("threads  train (examples/s)  inference (examples/s)");
+
+    for &threads in &options.threads {
+        let mut network = network(features.ncols(), labels.ncols());
+        let start = Instant::now();
+        for start in (0..rows).step_by(256) {
+            let end = (start + 256).min(rows);
+            let input = features.slice(s![start..end, ..]).to_owned();
+            let target = labels.slice(s![start..end, ..]).to_owned();
+            network.train_batch_parallel(&input, &target, threads);
+        }
+        let train = rows as f64 / start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        network.predict(&features, threads);
+        let inference = rows as f64 / start.elapsed().as_secs_f64();
+
+        println!("{:>7}  {:>18.0}  {:>22.0}", threads, train, inference);
+    }
+    Ok(())
+}
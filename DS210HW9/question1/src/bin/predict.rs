@@ -0,0 +1,118 @@
+use ndarray::{Array2, ArrayView1};
+use neural_network::activation::Activation;
+use neural_network::data::{load_image, load_mnist_data, IMAGE_SIDE};
+use neural_network::evaluate::{argmax, top_k};
+use neural_network::network::NeuralNetwork;
+use std::{env, error::Error};
+
+// Classifies digits with a model saved by the training binary.
+//   cargo run --bin predict -- <model> [--top <k>] <input>...
+// Inputs ending in .csv are read as MNIST rows (label first, then 784 pixels);
+// anything else is read as a 28x28 PGM or PNG image.
+struct Options {
+    model: String,
+    top: usize,
+    inputs: Vec<String>,
+}
+
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let model = args
+        .next()
+        .ok_or("usage: predict <model> [--top <k>] <input>...")?;
+    let mut options = Options {
+        model,
+        top: 3,
+        inputs: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => options.top = args.next().ok_or("--top needs a value")?.parse()?,
+            _ => options.inputs.push(arg),
+        }
+    }
+    if options.inputs.is_empty() {
+        return Err("no inputs to classify".into());
+    }
+    Ok(options)
+}
+
+// The k most likely classes, best first. Softmax outputs are already
+// probabilities; any other output layer is rescaled so its outputs sum to one.
+fn probabilities(output: ArrayView1<f32>, k: usize, softmax: bool) -> Vec<(usize, f32)> {
+    let total: f32 = if softmax { 1.0 } else { output.sum() };
+    top_k(output, k)
+        .into_iter()
+        .map(|class| {
+            let p = if total > 0.0 {
+                output[class] / total
+            } else {
+                0.0
+            };
+            (class, p)
+        })
+        .collect()
+}
+
+fn format_top(classes: &[(usize, f32)]) -> String {
+    classes
+        .iter()
+        .map(|(class, p)| format!("{} ({:.2}%)", class, p * 100.0))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options()?;
+    let network = NeuralNetwork::load(&options.model)?;
+    let softmax = network.output_activation() == Activation::Softmax;
+    if network.input_size() != IMAGE_SIDE * IMAGE_SIDE {
+        return Err(format!(
+            "{} expects {} features per row, but digits have {} pixels",
+            options.model,
+            network.input_size(),
+            IMAGE_SIDE * IMAGE_SIDE
+        )
+        .into());
+    }
+
+    for input in &options.inputs {
+        if input.to_ascii_lowercase().ends_with(".csv") {
+            let (features, labels) = load_mnist_data(input)?;
+            let pass = network.forward(&features);
+            let mut correct = 0;
+            for (i, (output, label)) in pass
+                .output()
+                .outer_iter()
+                .zip(labels.outer_iter())
+                .enumerate()
+            {
+                let classes = probabilities(output, options.top, softmax);
+                let actual = argmax(label);
+                if classes.first().map(|c| c.0) == Some(actual) {
+                    correct += 1;
+                }
+                println!(
+                    "{} row {} (label {}): {}",
+                    input,
+                    i + 1,
+                    actual,
+                    format_top(&classes)
+                );
+            }
+            println!(
+                "{}: {} of {} rows correct",
+                input,
+                correct,
+                features.nrows()
+            );
+        } else {
+            let pixels = load_image(input)?;
+            let row = Array2::from_shape_vec((1, IMAGE_SIDE * IMAGE_SIDE), pixels)?;
+            let pass = network.forward(&row);
+            let classes = probabilities(pass.output().row(0), options.top, softmax);
+            println!("{}: {}", input, format_top(&classes));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use ndarray::s;
+use neural_network::activation::Activation;
+use neural_network::data::{load_examples, train_validation_split};
+use neural_network::search::{search, write_leaderboard, SearchSpace, TrialResult};
+use neural_network::train::TrainConfig;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::str::FromStr;
+use std::{env, error::Error, thread};
+
+// Hyperparameter search on the MNIST training set. Every trial is scored on a
+// validation split of it; the test set is never looked at.
+//   cargo run --release --bin tune -- [--random <n>] [flags]
+// Flags:
+//   --random <n>                 sample n trials instead of trying the whole grid
+//   --seed <n>                   seeds the split, the sampling and every trial (0)
+//   --epochs <n>                 maximum epochs per trial (3)
+//   --patience <n>               stop a trial early without a better validation loss
+//   --validation <fraction>      share of the training set to score on (0.1)
+//   --rows <n>                   only use the first n training rows, for a quick look
+//   --leaderboard <path>         where to write the ranked results (leaderboard.csv)
+//   --idx <dir>                  read the IDX files from dir instead of the CSV
+//   --threads <n>                threads to share each batch between
+// The search space, each a comma separated list of values to try (defaults in
+// `SearchSpace::default`):
+//   --hidden <sizes>             hidden layer sizes joined by '-', e.g. 256-128,128
+//                                (the flag can be repeated)
+//   --learning-rate <rates>, --activation <names>, --optimizer <names>,
+//   --batch-size <sizes>, --dropout <rates>, --l2 <strengths>
+struct Options {
+    space: SearchSpace,
+    random: Option<usize>,
+    seed: u64,
+    config: TrainConfig,
+    validation: f32,
+    rows: Option<usize>,
+    leaderboard: String,
+    idx: Option<String>,
+}
+
+fn list<T: FromStr>(value: &str) -> Result<Vec<T>, Box<dyn Error>>
+where
+    T::Err: Error + 'static,
+{
+    value
+        .split(',')
+        .map(|item| item.trim().parse().map_err(|e: T::Err| e.into()))
+        .collect()
+}
+
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        space: SearchSpace::default(),
+        random: None,
+        seed: 0,
+        config: TrainConfig {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..TrainConfig::default()
+        },
+        validation: 0.1,
+        rows: None,
+        leaderboard: "leaderboard.csv".to_string(),
+        idx: None,
+    };
+    let mut hidden = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--random" => options.random = Some(value()?.parse()?),
+            "--seed" => options.seed = value()?.parse()?,
+            "--epochs" => options.config.epochs = value()?.parse()?,
+            "--patience" => options.config.patience = Some(value()?.parse()?),
+            "--validation" => options.validation = value()?.parse()?,
+            "--rows" => options.rows = Some(value()?.parse()?),
+            "--leaderboard" => options.leaderboard = value()?,
+            "--idx" => options.idx = Some(value()?),
+            "--threads" => options.config.threads = value()?.parse()?,
+            "--hidden" => {
+                for sizes in value()?.split(',') {
+                    hidden.push(
+                        sizes
+                            .split('-')
+                            .map(|n| n.parse())
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+            }
+            "--learning-rate" => options.space.learning_rates = list(&value()?)?,
+            "--activation" => {
+                options.space.activations = value()?
+                    .split(',')
+                    .map(|name| name.parse::<Activation>())
+                    .collect::<Result<_, _>>()?
+            }
+            "--optimizer" => {
+                options.space.optimizers = value()?.split(',').map(String::from).collect()
+            }
+            "--batch-size" => options.space.batch_sizes = list(&value()?)?,
+            "--dropout" => options.space.dropout = list(&value()?)?,
+            "--l2" => options.space.l2 = list(&value()?)?,
+            other => return Err(format!("unknown argument {}", other).into()),
+        }
+    }
+    if !hidden.is_empty() {
+        options.space.hidden = hidden;
+    }
+    Ok(options)
+}
+
+fn describe(result: &TrialResult) -> String {
+    let trial = &result.trial;
+    format!(
+        "{:>6.2}%  loss {:.4}  hidden {} {:?} {} lr {} batch {} dropout {} l2 {} ({:.1}s)",
+        result.validation_accuracy,
+        result.validation_loss,
+        trial.hidden_sizes(),
+        trial.activation,
+        trial.optimizer,
+        trial.learning_rate,
+        trial.batch_size,
+        trial.dropout,
+        trial.l2,
+        result.seconds
+    )
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options()?;
+    let (features, labels) = load_examples(options.idx.as_deref(), "train", None)?;
+    let rows = options
+        .rows
+        .unwrap_or(features.nrows())
+        .min(features.nrows());
+    let features = features.slice(s![..rows, ..]).to_owned();
+    let labels = labels.slice(s![..rows, ..]).to_owned();
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let ((train_features, train_labels), (validation_features, validation_labels)) =
+        train_validation_split(&features, &labels, options.validation, &mut rng);
+    if validation_features.nrows() == 0 {
+        return Err("the validation split is empty; raise --validation".into());
+    }
+
+    let trials = match options.random {
+        Some(count) => options.space.random(count, options.seed),
+        None => options.space.grid(options.seed),
+    };
+    println!(
+        "{} trials on {} training and {} validation rows",
+        trials.len(),
+        train_features.nrows(),
+        validation_features.nrows()
+    );
+
+    let mut finished = 0;
+    let results = search(
+        &trials,
+        (&train_features, &train_labels),
+        (&validation_features, &validation_labels),
+        &options.config,
+        |result| {
+            finished += 1;
+            println!("[{}/{}] {}", finished, trials.len(), describe(result));
+        },
+    )?;
+
+    write_leaderboard(&results, &options.leaderboard)?;
+    println!("\nTop trials:");
+    for (rank, result) in results.iter().take(5).enumerate() {
+        println!("{:>3}. {}", rank + 1, describe(result));
+    }
+    println!("Wrote leaderboard to {}", options.leaderboard);
+    Ok(())
+}
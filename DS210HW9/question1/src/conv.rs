@@ -0,0 +1,433 @@
+use ndarray::{Array1, Array2, ArrayD, Axis};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::activation::Activation;
+use crate::init::Initializer;
+use crate::layer::Cache;
+
+// Image layers still take one row per sample. A row holds the image flattened
+// channel by channel, then row by row, so a 28x28 MNIST digit is Shape::new(1, 28, 28)
+// and its 784 pixels are already in the right order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub channels: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Shape {
+    pub fn new(channels: usize, height: usize, width: usize) -> Self {
+        Shape {
+            channels,
+            height,
+            width,
+        }
+    }
+
+    // Number of values in one flattened sample.
+    pub fn size(&self) -> usize {
+        self.channels * self.height * self.width
+    }
+
+    fn index(&self, channel: usize, y: usize, x: usize) -> usize {
+        (channel * self.height + y) * self.width + x
+    }
+}
+
+// 2D convolution computed with im2col: every kernel-sized patch of the input
+// becomes a row of a matrix, so the whole layer is one matrix product with the
+// (channels * kernel * kernel, filters) weight matrix.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Conv2D {
+    pub input: Shape,
+    pub filters: usize,
+    pub kernel: usize,
+    pub stride: usize,
+    pub padding: usize,
+    pub weights: Array2<f32>,
+    pub bias: Array1<f32>,
+    pub activation: Activation,
+    pub weight_init: Initializer,
+    pub bias_init: Initializer,
+}
+
+impl Conv2D {
+    pub fn new(
+        input: Shape,
+        filters: usize,
+        kernel: usize,
+        activation: Activation,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        let mut conv = Conv2D {
+            input,
+            filters,
+            kernel,
+            stride: 1,
+            padding: 0,
+            weights: Array2::zeros((input.channels * kernel * kernel, filters)),
+            bias: Array1::zeros(filters),
+            activation,
+            weight_init: Initializer::Uniform,
+            bias_init: Initializer::Zeros,
+        };
+        conv.initialize(rng);
+        conv
+    }
+
+    pub fn with_initializers(
+        mut self,
+        weights: Initializer,
+        bias: Initializer,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        self.weight_init = weights;
+        self.bias_init = bias;
+        self.initialize(rng);
+        self
+    }
+
+    // Each output sees channels x kernel x kernel inputs. The uniform default keeps
+    // the range the layer has always had, with the filter count as fan out.
+    pub(crate) fn initialize(&mut self, rng: &mut dyn RngCore) {
+        let (fan_in, filters) = self.weights.dim();
+        let fan_out = match self.weight_init {
+            Initializer::Uniform => filters,
+            _ => filters * self.kernel * self.kernel,
+        };
+        self.weights = self
+            .weight_init
+            .weights(self.weights.dim(), fan_in, fan_out, rng);
+        self.bias = self.bias_init.bias(filters, rng);
+    }
+
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        assert!(stride > 0, "stride must be positive");
+        self.stride = stride;
+        self
+    }
+
+    // Zero padding added on every side of the input.
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn output(&self) -> Shape {
+        let padded_height = self.input.height + 2 * self.padding;
+        let padded_width = self.input.width + 2 * self.padding;
+        assert!(
+            self.kernel <= padded_height && self.kernel <= padded_width,
+            "kernel is larger than the padded input"
+        );
+        Shape::new(
+            self.filters,
+            (padded_height - self.kernel) / self.stride + 1,
+            (padded_width - self.kernel) / self.stride + 1,
+        )
+    }
+
+    // Calls `f(patch row, patch column, input index)` for every kernel position
+    // that falls inside the (unpadded) input.
+    fn for_each_patch<F: FnMut(usize, usize, usize)>(&self, mut f: F) {
+        let out = self.output();
+        let k = self.kernel;
+        for oy in 0..out.height {
+            for ox in 0..out.width {
+                let row = oy * out.width + ox;
+                for c in 0..self.input.channels {
+                    for ky in 0..k {
+                        let y = (oy * self.stride + ky).checked_sub(self.padding);
+                        for kx in 0..k {
+                            let x = (ox * self.stride + kx).checked_sub(self.padding);
+                            if let (Some(y), Some(x)) = (y, x) {
+                                if y < self.input.height && x < self.input.width {
+                                    f(row, (c * k + ky) * k + kx, self.input.index(c, y, x));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn im2col(&self, input: &Array2<f32>) -> Array2<f32> {
+        let positions = self.output().height * self.output().width;
+        let mut cols = Array2::zeros((input.nrows() * positions, self.weights.nrows()));
+        for (sample, image) in input.outer_iter().enumerate() {
+            let offset = sample * positions;
+            self.for_each_patch(|row, col, i| cols[[offset + row, col]] = image[i]);
+        }
+        cols
+    }
+
+    // The reverse of im2col: adds every patch value back onto the input pixel it
+    // came from.
+    fn col2im(&self, cols: &Array2<f32>, samples: usize) -> Array2<f32> {
+        let positions = self.output().height * self.output().width;
+        let mut images = Array2::zeros((samples, self.input.size()));
+        for (sample, mut image) in images.outer_iter_mut().enumerate() {
+            let offset = sample * positions;
+            self.for_each_patch(|row, col, i| image[i] += cols[[offset + row, col]]);
+        }
+        images
+    }
+
+    pub(crate) fn forward(&self, input: &Array2<f32>) -> (Array2<f32>, Cache) {
+        let positions = self.output().height * self.output().width;
+        let cols = self.im2col(input);
+        // One row per (sample, position) and one column per filter; regroup into one
+        // row per sample laid out filter by filter.
+        let z_cols = cols.dot(&self.weights) + &self.bias;
+        let z = Array2::from_shape_fn((input.nrows(), self.filters * positions), |(s, j)| {
+            z_cols[[s * positions + j % positions, j / positions]]
+        });
+        (self.activation.forward(&z), Cache::Conv { cols, z })
+    }
+
+    pub(crate) fn backward(
+        &self,
+        cols: &Array2<f32>,
+        z: &Array2<f32>,
+        output: &Array2<f32>,
+        grad: &Array2<f32>,
+    ) -> (Array2<f32>, Vec<ArrayD<f32>>) {
+        let positions = self.output().height * self.output().width;
+        let delta = self.activation.backward(z, output, grad);
+        let samples = delta.nrows();
+        let delta_cols = Array2::from_shape_fn((samples * positions, self.filters), |(r, f)| {
+            delta[[r / positions, f * positions + r % positions]]
+        });
+
+        let grad_weights = cols.t().dot(&delta_cols);
+        let grad_bias = delta_cols.sum_axis(Axis(0));
+        let grad_input = self.col2im(&delta_cols.dot(&self.weights.t()), samples);
+        (
+            grad_input,
+            vec![grad_weights.into_dyn(), grad_bias.into_dyn()],
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PoolKind {
+    Max,
+    Average,
+}
+
+// Max or average pooling over `size` x `size` windows of each channel.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pool {
+    pub input: Shape,
+    pub size: usize,
+    pub stride: usize,
+    pub kind: PoolKind,
+}
+
+impl Pool {
+    // Non-overlapping windows: the stride defaults to the window size.
+    pub fn max(input: Shape, size: usize) -> Self {
+        Pool {
+            input,
+            size,
+            stride: size,
+            kind: PoolKind::Max,
+        }
+    }
+
+    pub fn average(input: Shape, size: usize) -> Self {
+        Pool {
+            kind: PoolKind::Average,
+            ..Pool::max(input, size)
+        }
+    }
+
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        assert!(stride > 0, "stride must be positive");
+        self.stride = stride;
+        self
+    }
+
+    pub fn output(&self) -> Shape {
+        assert!(
+            self.size <= self.input.height && self.size <= self.input.width,
+            "pooling window is larger than the input"
+        );
+        Shape::new(
+            self.input.channels,
+            (self.input.height - self.size) / self.stride + 1,
+            (self.input.width - self.size) / self.stride + 1,
+        )
+    }
+
+    // Input indices covered by the window behind output index `j`.
+    fn window(&self, j: usize) -> impl Iterator<Item = usize> + '_ {
+        let out = self.output();
+        let (c, oy, ox) = (
+            j / (out.height * out.width),
+            j / out.width % out.height,
+            j % out.width,
+        );
+        (0..self.size).flat_map(move |dy| {
+            (0..self.size).map(move |dx| {
+                self.input
+                    .index(c, oy * self.stride + dy, ox * self.stride + dx)
+            })
+        })
+    }
+
+    pub(crate) fn forward(&self, input: &Array2<f32>) -> (Array2<f32>, Cache) {
+        let outputs = self.output().size();
+        let mut output = Array2::zeros((input.nrows(), outputs));
+        match self.kind {
+            PoolKind::Max => {
+                // Remember which input won each window; only it gets the gradient.
+                let mut winners = Array2::zeros((input.nrows(), outputs));
+                for (s, image) in input.outer_iter().enumerate() {
+                    for j in 0..outputs {
+                        let best = self
+                            .window(j)
+                            .max_by(|&a, &b| image[a].partial_cmp(&image[b]).unwrap())
+                            .unwrap();
+                        output[[s, j]] = image[best];
+                        winners[[s, j]] = best;
+                    }
+                }
+                (
+                    output,
+                    Cache::Pool {
+                        winners: Some(winners),
+                    },
+                )
+            }
+            PoolKind::Average => {
+                let area = (self.size * self.size) as f32;
+                for (s, image) in input.outer_iter().enumerate() {
+                    for j in 0..outputs {
+                        output[[s, j]] = self.window(j).map(|i| image[i]).sum::<f32>() / area;
+                    }
+                }
+                (output, Cache::Pool { winners: None })
+            }
+        }
+    }
+
+    pub(crate) fn backward(
+        &self,
+        winners: Option<&Array2<usize>>,
+        grad: &Array2<f32>,
+    ) -> Array2<f32> {
+        let mut grad_input = Array2::zeros((grad.nrows(), self.input.size()));
+        let area = (self.size * self.size) as f32;
+        for (s, row) in grad.outer_iter().enumerate() {
+            for (j, &g) in row.iter().enumerate() {
+                match winners {
+                    Some(winners) => grad_input[[s, winners[[s, j]]]] += g,
+                    None => {
+                        for i in self.window(j) {
+                            grad_input[[s, i]] += g / area;
+                        }
+                    }
+                }
+            }
+        }
+        grad_input
+    }
+}
+
+// Marks where image layers end and dense layers begin. Rows are already stored
+// flat, so this only records the shape being flattened.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Flatten {
+    pub input: Shape,
+}
+
+impl Flatten {
+    pub fn new(input: Shape) -> Self {
+        Flatten { input }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::{Dense, Layer};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    #[test]
+    fn test_conv_output_shape() {
+        let conv = Conv2D::new(Shape::new(1, 28, 28), 8, 5, Activation::Relu, &mut rng());
+        assert_eq!(conv.output(), Shape::new(8, 24, 24));
+        let padded = Conv2D::new(Shape::new(3, 28, 28), 4, 3, Activation::Relu, &mut rng())
+            .with_padding(1)
+            .with_stride(2);
+        assert_eq!(padded.output(), Shape::new(4, 14, 14));
+    }
+
+    #[test]
+    fn test_conv_matches_direct_convolution() {
+        // A single 2x2 kernel of ones sums each window.
+        let mut conv = Conv2D::new(Shape::new(1, 3, 3), 1, 2, Activation::Identity, &mut rng());
+        conv.weights.fill(1.0);
+        let input = Array2::from_shape_vec((1, 9), (1..=9).map(|x| x as f32).collect()).unwrap();
+        let (output, _) = conv.forward(&input);
+        assert_eq!(output.row(0).to_vec(), vec![12.0, 16.0, 24.0, 28.0]);
+    }
+
+    #[test]
+    fn test_pool_forward() {
+        let input = Array2::from_shape_vec((1, 16), (0..16).map(|x| x as f32).collect()).unwrap();
+        let shape = Shape::new(1, 4, 4);
+        let (max, _) = Pool::max(shape, 2).forward(&input);
+        assert_eq!(max.row(0).to_vec(), vec![5.0, 7.0, 13.0, 15.0]);
+        let (average, _) = Pool::average(shape, 2).forward(&input);
+        assert_eq!(average.row(0).to_vec(), vec![2.5, 4.5, 10.5, 12.5]);
+    }
+
+    #[test]
+    fn test_conv_network_learns() {
+        use crate::loss::Loss;
+        use crate::network::NeuralNetwork;
+        use crate::optimizer::Adam;
+
+        // Bright pixels in the left or right half of a 1x4x4 image.
+        let shape = Shape::new(1, 4, 4);
+        let input = Array2::from_shape_fn((8, 16), |(s, i)| {
+            let left = i % 4 < 2;
+            if (s % 2 == 0) == left && (i + s) % 3 != 0 {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        let target = Array2::from_shape_fn((8, 2), |(s, j)| (s % 2 == j) as u8 as f32);
+
+        let conv = Conv2D::new(shape, 4, 3, Activation::Relu, &mut rng()).with_padding(1);
+        let pool = Pool::max(conv.output(), 2);
+        let features = pool.output().size();
+        let flatten = Flatten::new(pool.output());
+        let layers = vec![
+            Layer::Conv2D(conv),
+            Layer::Pool(pool),
+            Layer::Flatten(flatten),
+            Layer::Dense(Dense::new(features, 2, Activation::Softmax, &mut rng())),
+        ];
+        let mut network = NeuralNetwork::from_layers(layers, 0.0)
+            .with_loss(Loss::SoftmaxCrossEntropy)
+            .with_optimizer(Box::new(Adam::new(0.05)));
+
+        for _ in 0..200 {
+            network.train_batch(&input, &target);
+        }
+        let (loss, accuracy) = network.score(&input, &target);
+        assert!(loss < 0.1, "loss {}", loss);
+        assert_eq!(accuracy, 100.0);
+    }
+}
@@ -0,0 +1,214 @@
+use ndarray::{Array2, Axis};
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use std::path::Path;
+use std::{error::Error, fs, fs::File};
+
+use crate::dataset::Dataset;
+
+pub const IMAGE_SIDE: usize = 28;
+
+// Features and one-hot labels, one row per example.
+pub type Examples = (Array2<f32>, Array2<f32>);
+
+// Reads an MNIST CSV file, which must have 784 pixels and a digit label per row.
+// The sizes are fixed because a CSV row does not say how its pixels make up an
+// image; other image sizes come as IDX files through `load_examples`.
+pub fn load_mnist_data(file_path: &str) -> Result<Examples, Box<dyn Error>> {
+    Dataset::csv(file_path)
+        .with_features(IMAGE_SIDE * IMAGE_SIDE)
+        .with_classes(10)
+        .load()
+}
+
+// Reads the training or test set (`set` is "train" or "t10k", as in the IDX file
+// names) from the IDX files in `idx`, or from the MNIST CSVs without it. IDX files
+// can hold any image size and number of classes, which are inferred from the data
+// unless `like` gives the (features, classes) to require.
+pub fn load_examples(
+    idx: Option<&str>,
+    set: &str,
+    like: Option<(usize, usize)>,
+) -> Result<Examples, Box<dyn Error>> {
+    match idx {
+        Some(dir) => {
+            let mut dataset = Dataset::idx(
+                &format!("{}/{}-images-idx3-ubyte", dir, set),
+                &format!("{}/{}-labels-idx1-ubyte", dir, set),
+            );
+            if let Some((features, classes)) = like {
+                dataset = dataset.with_features(features).with_classes(classes);
+            }
+            dataset.load()
+        }
+        None if set == "train" => load_mnist_data("../MNIST_CSV/mnist_train.csv"),
+        None => load_mnist_data("../MNIST_CSV/mnist_test.csv"),
+    }
+}
+
+// Shuffles the rows and holds back `fraction` of them, returning
+// ((train features, train labels), (validation features, validation labels)).
+pub fn train_validation_split(
+    features: &Array2<f32>,
+    labels: &Array2<f32>,
+    fraction: f32,
+    rng: &mut dyn RngCore,
+) -> (Examples, Examples) {
+    let mut order: Vec<usize> = (0..features.nrows()).collect();
+    order.shuffle(rng);
+    let held_out = (features.nrows() as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+    let (validation, train) = order.split_at(held_out);
+    (
+        (
+            features.select(Axis(0), train),
+            labels.select(Axis(0), train),
+        ),
+        (
+            features.select(Axis(0), validation),
+            labels.select(Axis(0), validation),
+        ),
+    )
+}
+
+// Scales 0-255 grey levels to the 0-1 range the network is trained on.
+pub fn normalize(pixels: &[u8]) -> Vec<f32> {
+    pixels.iter().map(|&x| x as f32 / 255.0).collect()
+}
+
+// Reads a 28x28 greyscale PGM (P2 or P5) or PNG image as one normalized row of
+// 784 pixels. Colour PNGs are converted to grey. Like MNIST, the digit should be
+// light on a dark background.
+pub fn load_image(path: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let (width, height, pixels) = match extension.as_deref() {
+        Some("pgm") => parse_pgm(&fs::read(path)?)?,
+        Some("png") => read_png(path)?,
+        _ => return Err(format!("{}: expected a .pgm or .png image", path).into()),
+    };
+    if (width, height) != (IMAGE_SIDE, IMAGE_SIDE) {
+        return Err(format!(
+            "{} is {}x{}, expected {}x{}",
+            path, width, height, IMAGE_SIDE, IMAGE_SIDE
+        )
+        .into());
+    }
+    Ok(normalize(&pixels))
+}
+
+fn parse_pgm(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>), Box<dyn Error>> {
+    // The header is four whitespace separated tokens (magic, width, height, maxval),
+    // and may contain `#` comments running to the end of the line.
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while tokens.len() < 4 {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+            if bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err("truncated PGM header".into());
+        }
+        tokens.push(std::str::from_utf8(&bytes[start..pos])?);
+    }
+    let width: usize = tokens[1].parse()?;
+    let height: usize = tokens[2].parse()?;
+    let max: u32 = tokens[3].parse()?;
+    if max == 0 || max > 65535 {
+        return Err(format!("bad PGM maximum value {}", max).into());
+    }
+
+    // A single whitespace byte separates the header from a P5 raster.
+    let raster = || bytes.get(pos + 1..).ok_or("truncated PGM data");
+    let values: Vec<u32> = match tokens[0] {
+        "P2" => std::str::from_utf8(&bytes[pos..])?
+            .split_ascii_whitespace()
+            .map(|v| v.parse())
+            .collect::<Result<_, _>>()?,
+        "P5" if max < 256 => raster()?.iter().map(|&b| b as u32).collect(),
+        "P5" => raster()?
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as u32)
+            .collect(),
+        other => return Err(format!("unsupported PGM type {}", other).into()),
+    };
+    if values.len() < width * height {
+        return Err("truncated PGM data".into());
+    }
+    let pixels = values[..width * height]
+        .iter()
+        .map(|&v| (v.min(max) * 255 / max) as u8)
+        .collect();
+    Ok((width, height, pixels))
+}
+
+fn read_png(path: &str) -> Result<(usize, usize, Vec<u8>), Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let bytes = &buf[..info.buffer_size()];
+
+    // Drop any alpha channel and average the colour channels.
+    let pixels = match info.color_type {
+        png::ColorType::Grayscale | png::ColorType::Indexed => bytes.to_vec(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|px| px[0]).collect(),
+        png::ColorType::Rgb => bytes.chunks_exact(3).map(grey).collect(),
+        png::ColorType::Rgba => bytes.chunks_exact(4).map(grey).collect(),
+    };
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+fn grey(px: &[u8]) -> u8 {
+    ((px[0] as u32 + px[1] as u32 + px[2] as u32) / 3) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_parse_pgm() {
+        let ascii = b"P2\n# a comment\n2 2\n15\n0 15\n5 10\n";
+        let (width, height, pixels) = parse_pgm(ascii).unwrap();
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(pixels, vec![0, 255, 85, 170]);
+
+        let mut binary = b"P5 2 1 255\n".to_vec();
+        binary.extend_from_slice(&[7, 200]);
+        assert_eq!(parse_pgm(&binary).unwrap().2, vec![7, 200]);
+
+        assert!(parse_pgm(b"P5 2 2").is_err());
+        assert!(parse_pgm(b"P5 2 2 255").is_err());
+    }
+
+    #[test]
+    fn test_train_validation_split_keeps_rows_together() {
+        let features = Array2::from_shape_fn((10, 2), |(i, _)| i as f32);
+        let labels = Array2::from_shape_fn((10, 1), |(i, _)| i as f32);
+        let ((train_x, train_y), (val_x, val_y)) =
+            train_validation_split(&features, &labels, 0.3, &mut StdRng::seed_from_u64(0));
+
+        assert_eq!((train_x.nrows(), val_x.nrows()), (7, 3));
+        assert_eq!(train_x.column(0), train_y.column(0));
+        assert_eq!(val_x.column(1), val_y.column(0));
+
+        let mut seen: Vec<f32> = train_y.iter().chain(val_y.iter()).copied().collect();
+        seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(seen, (0..10).map(|i| i as f32).collect::<Vec<_>>());
+    }
+}
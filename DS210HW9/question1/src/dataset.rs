@@ -0,0 +1,366 @@
+// Labelled image datasets, read one row at a time. A dataset is either a CSV file
+// with the label followed by the pixels on each line (the MNIST CSV layout), or
+// the original pair of IDX files. Every row is checked as it is read, and errors
+// name the file and row they came from.
+
+use csv::{ReaderBuilder, StringRecordsIntoIter};
+use ndarray::Array2;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+
+use crate::data::{normalize, Examples};
+
+// One labelled example, with its pixels normalized to 0-1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    pub features: Vec<f32>,
+    pub label: usize,
+}
+
+enum Source {
+    Csv(String),
+    Idx { images: String, labels: String },
+}
+
+pub struct Dataset {
+    source: Source,
+    features: Option<usize>,
+    classes: Option<usize>,
+}
+
+impl Dataset {
+    // A first line whose label is not a number is taken as a header and skipped.
+    pub fn csv(path: &str) -> Self {
+        Dataset {
+            source: Source::Csv(path.to_string()),
+            features: None,
+            classes: None,
+        }
+    }
+
+    // An IDX image file (e.g. train-images-idx3-ubyte) and its label file.
+    pub fn idx(images: &str, labels: &str) -> Self {
+        Dataset {
+            source: Source::Idx {
+                images: images.to_string(),
+                labels: labels.to_string(),
+            },
+            features: None,
+            classes: None,
+        }
+    }
+
+    // Requires exactly `features` pixels per row. Without it, the first row sets
+    // the width and every later row must match.
+    pub fn with_features(mut self, features: usize) -> Self {
+        self.features = Some(features);
+        self
+    }
+
+    // Requires every label to be below `classes`. Without it, `load` uses the
+    // largest label seen plus one.
+    pub fn with_classes(mut self, classes: usize) -> Self {
+        self.classes = Some(classes);
+        self
+    }
+
+    pub fn rows(&self) -> Result<Rows, Box<dyn Error>> {
+        let (path, reader) = match &self.source {
+            Source::Csv(path) => {
+                let records = ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_path(path)
+                    .map_err(|e| format!("{}: {}", path, e))?
+                    .into_records();
+                (path.clone(), Reader::Csv(records))
+            }
+            Source::Idx { images, labels } => {
+                let (images_file, dims) = open_idx(images, 3)?;
+                let (labels_file, label_dims) = open_idx(labels, 1)?;
+                if dims[0] != label_dims[0] {
+                    return Err(format!(
+                        "{} has {} images but {} has {} labels",
+                        images, dims[0], labels, label_dims[0]
+                    )
+                    .into());
+                }
+                let width = dims[1] * dims[2];
+                if let Some(features) = self.features.filter(|&features| features != width) {
+                    return Err(format!(
+                        "{}: images have {} pixels, expected {}",
+                        images, width, features
+                    )
+                    .into());
+                }
+                let reader = Reader::Idx {
+                    images: images_file,
+                    labels: labels_file,
+                    width,
+                    remaining: dims[0],
+                };
+                (images.clone(), reader)
+            }
+        };
+        Ok(Rows {
+            path,
+            reader,
+            features: self.features,
+            classes: self.classes,
+            row: 0,
+        })
+    }
+
+    // Reads every row into a feature matrix and one-hot labels.
+    pub fn load(&self) -> Result<Examples, Box<dyn Error>> {
+        let mut rows = self.rows()?;
+        let mut features = Vec::new();
+        let mut labels = Vec::new();
+        for example in &mut rows {
+            let example = example?;
+            features.extend(example.features);
+            labels.push(example.label);
+        }
+
+        let width = rows.features.unwrap_or(0);
+        let classes = self
+            .classes
+            .unwrap_or_else(|| labels.iter().max().map_or(0, |&label| label + 1));
+        let mut one_hot = Array2::zeros((labels.len(), classes));
+        for (i, &label) in labels.iter().enumerate() {
+            one_hot[[i, label]] = 1.0;
+        }
+        Ok((
+            Array2::from_shape_vec((labels.len(), width), features)?,
+            one_hot,
+        ))
+    }
+}
+
+// A label and its unscaled pixels.
+type RawRow = (usize, Vec<u8>);
+
+enum Reader {
+    Csv(StringRecordsIntoIter<File>),
+    Idx {
+        images: BufReader<File>,
+        labels: BufReader<File>,
+        width: usize,
+        remaining: usize,
+    },
+}
+
+// The rows of a dataset, in file order. Rows are numbered from 1.
+pub struct Rows {
+    path: String,
+    reader: Reader,
+    features: Option<usize>,
+    classes: Option<usize>,
+    row: usize,
+}
+
+impl Rows {
+    fn error(&self, message: String) -> Box<dyn Error> {
+        format!("{}: row {}: {}", self.path, self.row, message).into()
+    }
+
+    // Reads the next raw row, or None at the end of the file.
+    fn read(&mut self) -> Result<Option<RawRow>, Box<dyn Error>> {
+        match &mut self.reader {
+            Reader::Csv(records) => loop {
+                let record = match records.next() {
+                    Some(record) => record?,
+                    None => return Ok(None),
+                };
+                self.row = record
+                    .position()
+                    .map_or(self.row + 1, |p| p.line() as usize);
+                let first = record.get(0).unwrap_or("").trim();
+                let label = match first.parse() {
+                    Ok(label) => label,
+                    Err(_) if self.row == 1 => continue,
+                    Err(_) => return Err(self.error(format!("bad label '{}'", first))),
+                };
+                let mut pixels = Vec::with_capacity(record.len().saturating_sub(1));
+                for (column, value) in record.iter().enumerate().skip(1) {
+                    match value.trim().parse() {
+                        Ok(pixel) => pixels.push(pixel),
+                        Err(_) => {
+                            return Err(self.error(format!(
+                                "column {}: '{}' is not a pixel value (0-255)",
+                                column + 1,
+                                value
+                            )))
+                        }
+                    }
+                }
+                return Ok(Some((label, pixels)));
+            },
+            Reader::Idx {
+                images,
+                labels,
+                width,
+                remaining,
+            } => {
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                *remaining -= 1;
+                self.row += 1;
+                let mut label = [0];
+                let mut pixels = vec![0; *width];
+                let read = labels
+                    .read_exact(&mut label)
+                    .and_then(|_| images.read_exact(&mut pixels));
+                match read {
+                    Ok(()) => Ok(Some((label[0] as usize, pixels))),
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                        Err(self.error("file ends early".to_string()))
+                    }
+                    Err(e) => Err(self.error(e.to_string())),
+                }
+            }
+        }
+    }
+
+    fn next_example(&mut self) -> Result<Option<Example>, Box<dyn Error>> {
+        let (label, pixels) = match self.read()? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let width = *self.features.get_or_insert(pixels.len());
+        if pixels.len() != width {
+            let message = format!("expected {} pixels, found {}", width, pixels.len());
+            return Err(self.error(message));
+        }
+        if let Some(classes) = self.classes {
+            if label >= classes {
+                let message = format!("label {} is out of range for {} classes", label, classes);
+                return Err(self.error(message));
+            }
+        }
+        Ok(Some(Example {
+            features: normalize(&pixels),
+            label,
+        }))
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Result<Example, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_example().transpose()
+    }
+}
+
+// Opens an IDX file of unsigned bytes and returns it positioned at the data,
+// along with its dimensions. The header is two zero bytes, the type code 0x08
+// (unsigned byte), the number of dimensions, and then each dimension as a
+// big-endian u32.
+fn open_idx(
+    path: &str,
+    dimensions: usize,
+) -> Result<(BufReader<File>, Vec<usize>), Box<dyn Error>> {
+    let mut file = BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path, e))?);
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)
+        .map_err(|_| format!("{}: truncated IDX header", path))?;
+    if magic[..3] != [0, 0, 0x08] || magic[3] as usize != dimensions {
+        return Err(format!(
+            "{}: not an IDX file of bytes with {} dimensions",
+            path, dimensions
+        )
+        .into());
+    }
+    let mut dims = Vec::with_capacity(dimensions);
+    for _ in 0..dimensions {
+        let mut size = [0; 4];
+        file.read_exact(&mut size)
+            .map_err(|_| format!("{}: truncated IDX header", path))?;
+        dims.push(u32::from_be_bytes(size) as usize);
+    }
+    Ok((file, dims))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let dir = std::env::temp_dir().join(format!("nn_dataset_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_csv_infers_features_and_classes() {
+        let path = temp_file("good.csv", b"label,p1,p2,p3\n2,0,255,51\n0,0,0,0\n");
+        let (features, labels) = Dataset::csv(&path).load().unwrap();
+        assert_eq!(features.dim(), (2, 3));
+        assert_eq!(features.row(0).to_vec(), vec![0.0, 1.0, 0.2]);
+        assert_eq!(labels.dim(), (2, 3));
+        assert_eq!(labels.row(0).to_vec(), vec![0.0, 0.0, 1.0]);
+
+        let (_, labels) = Dataset::csv(&path).with_classes(10).load().unwrap();
+        assert_eq!(labels.ncols(), 10);
+    }
+
+    #[test]
+    fn test_csv_errors_name_the_row() {
+        let error = |contents: &[u8], dataset: fn(&str) -> Dataset| {
+            let path = temp_file("bad.csv", contents);
+            dataset(&path).load().unwrap_err().to_string()
+        };
+        let plain = |path: &str| Dataset::csv(path);
+        let digits = |path: &str| Dataset::csv(path).with_classes(10);
+
+        let message = error(b"1,0,0\n2,0\n", plain);
+        assert!(
+            message.ends_with("row 2: expected 2 pixels, found 1"),
+            "{}",
+            message
+        );
+        let message = error(b"1,0,0\n12,0,0\n", digits);
+        assert!(message.ends_with("row 2: label 12 is out of range for 10 classes"));
+        let message = error(b"1,0,0\n3,0,0\n4,0,300\n", plain);
+        assert!(message.ends_with("row 3: column 3: '300' is not a pixel value (0-255)"));
+        let message = error(b"1,0,0\nx,0,0\n", plain);
+        assert!(message.ends_with("row 2: bad label 'x'"));
+    }
+
+    #[test]
+    fn test_idx_files() {
+        let mut images = vec![0, 0, 8, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1];
+        images.extend_from_slice(&[0, 255, 0, 255]);
+        let labels = [0, 0, 8, 1, 0, 0, 0, 2, 7, 1];
+        let images = temp_file("images-idx3-ubyte", &images);
+        let labels = temp_file("labels-idx1-ubyte", &labels);
+
+        let (features, one_hot) = Dataset::idx(&images, &labels).load().unwrap();
+        assert_eq!(features.row(1).to_vec(), vec![0.0, 1.0]);
+        assert_eq!(one_hot.dim(), (2, 8));
+        assert_eq!((one_hot[[0, 7]], one_hot[[1, 1]]), (1.0, 1.0));
+
+        let message = Dataset::idx(&images, &labels)
+            .with_features(784)
+            .load()
+            .unwrap_err()
+            .to_string();
+        assert!(message.ends_with("images have 2 pixels, expected 784"));
+
+        let truncated = temp_file(
+            "short-idx3-ubyte",
+            &[0, 0, 8, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 9, 9, 9],
+        );
+        let message = Dataset::idx(&truncated, &labels)
+            .load()
+            .unwrap_err()
+            .to_string();
+        assert!(message.ends_with("row 2: file ends early"));
+    }
+}
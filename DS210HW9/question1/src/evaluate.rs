@@ -0,0 +1,263 @@
+use ndarray::{Array2, ArrayView1};
+use std::cmp::Ordering;
+use std::fmt;
+
+// Orders scores with NaN below everything else, so a diverged output never wins
+// and never panics the comparison.
+fn compare_scores(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+// The highest scoring class; ties go to the first.
+pub fn argmax(row: ArrayView1<f32>) -> usize {
+    top_k(row, 1)[0]
+}
+
+// The `k` highest scoring classes, best first, with ties in class order.
+pub fn top_k(row: ArrayView1<f32>, k: usize) -> Vec<usize> {
+    let mut classes: Vec<usize> = (0..row.len()).collect();
+    classes.sort_by(|&a, &b| compare_scores(row[b], row[a]));
+    classes.truncate(k);
+    classes
+}
+
+// Classification results for a set of network outputs against one-hot targets.
+pub struct Evaluation {
+    // confusion[[actual, predicted]] counts samples.
+    pub confusion: Array2<usize>,
+    // rank_counts[r] counts samples whose true class had the (r + 1)th highest output.
+    rank_counts: Vec<usize>,
+}
+
+impl Evaluation {
+    pub fn new(outputs: &Array2<f32>, targets: &Array2<f32>) -> Self {
+        let classes = outputs.ncols();
+        let mut confusion = Array2::zeros((classes, classes));
+        let mut rank_counts = vec![0; classes];
+        for (output, target) in outputs.outer_iter().zip(targets.outer_iter()) {
+            let actual = argmax(target);
+            let ranked = top_k(output, classes);
+            confusion[[actual, ranked[0]]] += 1;
+            let rank = ranked.iter().position(|&class| class == actual).unwrap();
+            rank_counts[rank] += 1;
+        }
+        Evaluation {
+            confusion,
+            rank_counts,
+        }
+    }
+
+    pub fn classes(&self) -> usize {
+        self.confusion.nrows()
+    }
+
+    pub fn total(&self) -> usize {
+        self.confusion.sum()
+    }
+
+    pub fn correct(&self) -> usize {
+        self.confusion.diag().sum()
+    }
+
+    // As a percentage, like the accuracies reported during training.
+    pub fn accuracy(&self) -> f32 {
+        ratio(self.correct(), self.total()) * 100.0
+    }
+
+    pub fn top_k_accuracy(&self, k: usize) -> f32 {
+        let hits: usize = self.rank_counts.iter().take(k).sum();
+        ratio(hits, self.total()) * 100.0
+    }
+
+    pub fn support(&self, class: usize) -> usize {
+        self.confusion.row(class).sum()
+    }
+
+    pub fn precision(&self, class: usize) -> f32 {
+        ratio(
+            self.confusion[[class, class]],
+            self.confusion.column(class).sum(),
+        )
+    }
+
+    pub fn recall(&self, class: usize) -> f32 {
+        ratio(self.confusion[[class, class]], self.support(class))
+    }
+
+    pub fn f1(&self, class: usize) -> f32 {
+        f1(self.precision(class), self.recall(class))
+    }
+
+    // Unweighted means over classes, so rare digits count as much as common ones.
+    pub fn macro_precision(&self) -> f32 {
+        self.mean_over_classes(|c| self.precision(c))
+    }
+
+    pub fn macro_recall(&self) -> f32 {
+        self.mean_over_classes(|c| self.recall(c))
+    }
+
+    pub fn macro_f1(&self) -> f32 {
+        self.mean_over_classes(|c| self.f1(c))
+    }
+
+    // Pooled over every sample. With exactly one label per sample, every false
+    // positive for one class is a false negative for another, so both pooled
+    // denominators are the sample count and micro precision, recall and F1 all
+    // equal the accuracy.
+    pub fn micro_precision(&self) -> f32 {
+        ratio(self.correct(), self.total())
+    }
+
+    pub fn micro_recall(&self) -> f32 {
+        ratio(self.correct(), self.total())
+    }
+
+    pub fn micro_f1(&self) -> f32 {
+        f1(self.micro_precision(), self.micro_recall())
+    }
+
+    fn mean_over_classes<F: Fn(usize) -> f32>(&self, metric: F) -> f32 {
+        (0..self.classes()).map(metric).sum::<f32>() / self.classes() as f32
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f32 / total as f32
+    }
+}
+
+fn f1(precision: f32, recall: f32) -> f32 {
+    if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Confusion matrix (rows: actual, columns: predicted)")?;
+        write!(f, "      ")?;
+        for class in 0..self.classes() {
+            write!(f, "{:>6}", class)?;
+        }
+        writeln!(f)?;
+        for (class, row) in self.confusion.outer_iter().enumerate() {
+            write!(f, "{:>6}", class)?;
+            for count in row {
+                write!(f, "{:>6}", count)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>9} {:>9} {:>9} {:>9} {:>9}",
+            "class", "precision", "recall", "f1", "support"
+        )?;
+        for class in 0..self.classes() {
+            writeln!(
+                f,
+                "{:>9} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+                class,
+                self.precision(class),
+                self.recall(class),
+                self.f1(class),
+                self.support(class)
+            )?;
+        }
+        writeln!(
+            f,
+            "{:>9} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+            "macro",
+            self.macro_precision(),
+            self.macro_recall(),
+            self.macro_f1(),
+            self.total()
+        )?;
+        writeln!(
+            f,
+            "{:>9} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+            "micro",
+            self.micro_precision(),
+            self.micro_recall(),
+            self.micro_f1(),
+            self.total()
+        )?;
+
+        writeln!(f)?;
+        for k in [1, 3, 5].into_iter().filter(|&k| k <= self.classes()) {
+            writeln!(f, "Top-{} accuracy: {:.2}%", k, self.top_k_accuracy(k))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_hot(classes: &[usize], width: usize) -> Array2<f32> {
+        Array2::from_shape_fn((classes.len(), width), |(i, j)| {
+            if classes[i] == j {
+                1.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    #[test]
+    fn test_metrics_from_confusion_matrix() {
+        // Actual:    0 0 0 1 1 2
+        // Predicted: 0 0 1 1 2 2, and the class 2 sample scores class 1 second.
+        let outputs = Array2::from_shape_vec(
+            (6, 3),
+            vec![
+                0.9, 0.1, 0.0, //
+                0.8, 0.2, 0.0, //
+                0.3, 0.6, 0.1, //
+                0.1, 0.8, 0.1, //
+                0.0, 0.4, 0.6, //
+                0.0, 0.3, 0.7,
+            ],
+        )
+        .unwrap();
+        let evaluation = Evaluation::new(&outputs, &one_hot(&[0, 0, 0, 1, 1, 2], 3));
+
+        assert_eq!(
+            evaluation.confusion,
+            Array2::from_shape_vec((3, 3), vec![2, 1, 0, 0, 1, 1, 0, 0, 1]).unwrap()
+        );
+        assert_eq!(evaluation.correct(), 4);
+        assert!((evaluation.precision(0) - 1.0).abs() < 1e-6);
+        assert!((evaluation.recall(0) - 2.0 / 3.0).abs() < 1e-6);
+        assert!((evaluation.precision(2) - 0.5).abs() < 1e-6);
+        assert!((evaluation.f1(1) - 0.5).abs() < 1e-6);
+        assert!((evaluation.macro_recall() - (2.0 / 3.0 + 0.5 + 1.0) / 3.0).abs() < 1e-6);
+        assert!((evaluation.micro_f1() - 4.0 / 6.0).abs() < 1e-6);
+        assert!((evaluation.top_k_accuracy(2) - 100.0).abs() < 1e-4);
+        assert!(evaluation.to_string().contains("Top-3 accuracy: 100.00%"));
+    }
+
+    #[test]
+    fn test_top_k_orders_by_score() {
+        let row = ndarray::arr1(&[0.1, 0.5, 0.2, 0.9]);
+        assert_eq!(top_k(row.view(), 3), vec![3, 1, 2]);
+        assert_eq!(argmax(row.view()), 3);
+
+        let diverged = ndarray::arr1(&[f32::NAN, 0.5, f32::NAN, 0.2]);
+        assert_eq!(top_k(diverged.view(), 3), vec![1, 3, 0]);
+        assert_eq!(argmax(diverged.view()), 1);
+    }
+}
@@ -0,0 +1,213 @@
+// Finite-difference checks of `NeuralNetwork::backward`. Every parameter is nudged
+// up and down by `step`, and the change in the loss is compared with the analytic
+// gradient. The training-mode forward pass is rerun from the same seed each time,
+// so dropout keeps the same mask and batch normalization uses batch statistics,
+// exactly as in the pass that was differentiated.
+
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::network::NeuralNetwork;
+
+// The element where the analytic and numeric gradients disagree the most.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientError {
+    pub parameter: usize,
+    pub index: usize,
+    pub analytic: f32,
+    pub numeric: f32,
+    pub error: f32,
+}
+
+// The loss the backward pass differentiates: the mean loss per sample of a
+// training pass plus the weight penalties.
+fn objective(network: &NeuralNetwork, input: &Array2<f32>, target: &Array2<f32>, seed: u64) -> f32 {
+    let pass = network.forward_training(input, &mut StdRng::seed_from_u64(seed));
+    network.loss().value(pass.output(), target) + network.penalty_value()
+}
+
+// Compares every parameter's gradient with a central difference and returns the
+// worst element. The error is relative once the gradients are larger than 1 and
+// absolute below that, so tiny gradients do not blow it up.
+pub fn check_gradients(
+    network: &mut NeuralNetwork,
+    input: &Array2<f32>,
+    target: &Array2<f32>,
+    step: f32,
+) -> Option<GradientError> {
+    let seed = 0;
+    let pass = network.forward_training(input, &mut StdRng::seed_from_u64(seed));
+    let grads = network.backward(input, &pass, target);
+
+    let mut worst: Option<GradientError> = None;
+    for (parameter, grad) in grads.iter().enumerate() {
+        for (index, &analytic) in grad.iter().enumerate() {
+            let nudge = |network: &mut NeuralNetwork, by: f32| {
+                *network.parameters_mut()[parameter]
+                    .iter_mut()
+                    .nth(index)
+                    .unwrap() += by;
+            };
+            nudge(network, step);
+            let up = objective(network, input, target, seed);
+            nudge(network, -2.0 * step);
+            let down = objective(network, input, target, seed);
+            nudge(network, step);
+
+            let numeric = (up - down) / (2.0 * step);
+            let error = (analytic - numeric).abs() / analytic.abs().max(numeric.abs()).max(1.0);
+            if worst.is_none_or(|worst| error > worst.error) {
+                worst = Some(GradientError {
+                    parameter,
+                    index,
+                    analytic,
+                    numeric,
+                    error,
+                });
+            }
+        }
+    }
+    worst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::Activation;
+    use crate::conv::{Conv2D, Flatten, Pool, Shape};
+    use crate::layer::{Dense, Layer};
+    use crate::loss::Loss;
+    use rand::Rng;
+
+    const STEP: f32 = 1e-3;
+    const TOLERANCE: f32 = 1e-2;
+
+    // Fresh weights from a fixed seed, so a kink of relu never happens to sit
+    // within `STEP` of a pre-activation on one run and not the next.
+    fn randomize_parameters(network: &mut NeuralNetwork, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for mut param in network.parameters_mut() {
+            param.mapv_inplace(|_| rng.gen_range(-0.8..0.8));
+        }
+    }
+
+    fn batch(rows: usize, columns: usize, seed: u64) -> Array2<f32> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Array2::from_shape_fn((rows, columns), |_| rng.gen_range(-1.0..1.0))
+    }
+
+    fn one_hot(rows: usize, classes: usize) -> Array2<f32> {
+        Array2::from_shape_fn((rows, classes), |(i, j)| {
+            ((i * 2 + 1) % classes == j) as u8 as f32
+        })
+    }
+
+    fn assert_gradients_match(mut network: NeuralNetwork, inputs: usize, name: &str) {
+        randomize_parameters(&mut network, 7);
+        let input = batch(5, inputs, 11);
+        let target = one_hot(5, 3);
+        let worst = check_gradients(&mut network, &input, &target, STEP).unwrap();
+        assert!(worst.error < TOLERANCE, "{}: {:?}", name, worst);
+    }
+
+    const HIDDEN: [Activation; 5] = [
+        Activation::Sigmoid,
+        Activation::Relu,
+        Activation::LeakyRelu(0.1),
+        Activation::Tanh,
+        Activation::Identity,
+    ];
+
+    #[test]
+    fn test_every_activation_and_loss() {
+        let outputs = [
+            (Loss::Mse, Activation::Sigmoid),
+            (Loss::Mse, Activation::Softmax),
+            (Loss::Mse, Activation::Tanh),
+            (Loss::Mse, Activation::Identity),
+            (Loss::BinaryCrossEntropy, Activation::Sigmoid),
+            (Loss::BinaryCrossEntropy, Activation::Softmax),
+            (Loss::SoftmaxCrossEntropy, Activation::Softmax),
+            (Loss::SoftmaxCrossEntropy, Activation::Sigmoid),
+        ];
+        for hidden in HIDDEN {
+            for (loss, output) in outputs {
+                let network = NeuralNetwork::new(&[4, 5, 3], 0.0)
+                    .with_activations(&[hidden, output])
+                    .with_loss(loss);
+                let name = format!("{:?} hidden, {:?} output, {:?}", hidden, output, loss);
+                assert_gradients_match(network, 4, &name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regularized_layers() {
+        for hidden in HIDDEN {
+            let network = || {
+                NeuralNetwork::new(&[4, 6, 5, 3], 0.0)
+                    .with_activations(&[hidden, hidden, Activation::Softmax])
+                    .with_loss(Loss::SoftmaxCrossEntropy)
+            };
+            assert_gradients_match(network().with_dropout(0.3), 4, "dropout");
+            assert_gradients_match(network().with_batch_norm(), 4, "batch norm");
+            assert_gradients_match(network().without_bias(), 4, "no bias");
+            assert_gradients_match(network().with_penalty(0.01, 0.1), 4, "penalty");
+            assert_gradients_match(
+                network()
+                    .with_batch_norm()
+                    .with_dropout(0.3)
+                    .with_penalty(0.01, 0.1),
+                4,
+                "everything",
+            );
+        }
+    }
+
+    #[test]
+    fn test_convolutional_layers() {
+        let shape = Shape::new(2, 5, 5);
+        let mut rng = StdRng::seed_from_u64(0);
+        for pool in [Pool::max, Pool::average] {
+            let conv = Conv2D::new(shape, 3, 3, Activation::Tanh, &mut rng).with_padding(1);
+            let pool = pool(conv.output(), 2).with_stride(1);
+            let flatten = Flatten::new(pool.output());
+            let features = pool.output().size();
+            let layers = vec![
+                Layer::Conv2D(conv),
+                Layer::Pool(pool),
+                Layer::Flatten(flatten),
+                Layer::Dense(Dense::new(features, 3, Activation::Softmax, &mut rng)),
+            ];
+            let network = NeuralNetwork::from_layers(layers, 0.0)
+                .with_loss(Loss::SoftmaxCrossEntropy)
+                .with_penalty(0.0, 0.1);
+            assert_gradients_match(network, shape.size(), "convolution");
+        }
+    }
+
+    #[test]
+    fn test_stacked_convolutions() {
+        // The second convolution checks the first one's input gradient, and the
+        // first covers a stride with padding.
+        let shape = Shape::new(2, 6, 6);
+        let mut rng = StdRng::seed_from_u64(1);
+        let strided = Conv2D::new(shape, 3, 3, Activation::LeakyRelu(0.1), &mut rng)
+            .with_padding(1)
+            .with_stride(2);
+        let conv = Conv2D::new(strided.output(), 2, 2, Activation::Tanh, &mut rng);
+        let pool = Pool::average(conv.output(), 2);
+        let flatten = Flatten::new(pool.output());
+        let features = pool.output().size();
+        let layers = vec![
+            Layer::Conv2D(strided),
+            Layer::Conv2D(conv),
+            Layer::Pool(pool),
+            Layer::Flatten(flatten),
+            Layer::Dense(Dense::new(features, 3, Activation::Softmax, &mut rng)),
+        ];
+        let network = NeuralNetwork::from_layers(layers, 0.0).with_loss(Loss::SoftmaxCrossEntropy);
+        assert_gradients_match(network, shape.size(), "stacked convolutions");
+    }
+}
@@ -0,0 +1,155 @@
+use ndarray::{Array, Array1, Array2};
+use ndarray_rand::rand_distr::{Normal, Uniform};
+use ndarray_rand::RandomExt;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// How a layer's weights or biases start out. `fan_in` and `fan_out` are the
+// number of inputs feeding each output and outputs fed by each input.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Initializer {
+    // The range this network has always used: uniform in +-sqrt(1 / (fan_in + fan_out)).
+    Uniform,
+    XavierUniform,
+    XavierNormal,
+    // Scaled for relu, which zeroes half of its inputs.
+    HeUniform,
+    HeNormal,
+    LeCun,
+    // A random (semi-)orthogonal matrix, so every direction keeps its length.
+    Orthogonal,
+    Zeros,
+    Constant(f32),
+}
+
+impl Initializer {
+    pub fn weights(
+        &self,
+        shape: (usize, usize),
+        fan_in: usize,
+        fan_out: usize,
+        rng: &mut dyn RngCore,
+    ) -> Array2<f32> {
+        let (fan_in, fan_out) = (fan_in.max(1) as f32, fan_out.max(1) as f32);
+        let uniform = |limit: f32, rng: &mut dyn RngCore| {
+            Array::random_using(shape, Uniform::new_inclusive(-limit, limit), rng)
+        };
+        let normal = |std: f32, rng: &mut dyn RngCore| {
+            Array::random_using(shape, Normal::new(0.0, std).unwrap(), rng)
+        };
+        match *self {
+            Initializer::Uniform => uniform((1.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::XavierUniform => uniform((6.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::XavierNormal => normal((2.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::HeUniform => uniform((6.0 / fan_in).sqrt(), rng),
+            Initializer::HeNormal => normal((2.0 / fan_in).sqrt(), rng),
+            Initializer::LeCun => normal((1.0 / fan_in).sqrt(), rng),
+            Initializer::Orthogonal => orthogonal(shape, rng),
+            Initializer::Zeros => Array2::zeros(shape),
+            Initializer::Constant(value) => Array2::from_elem(shape, value),
+        }
+    }
+
+    // Biases are a single row whose fans are both the layer's width.
+    pub fn bias(&self, size: usize, rng: &mut dyn RngCore) -> Array1<f32> {
+        let row = self.weights((1, size), size, size, rng);
+        Array1::from_vec(row.into_raw_vec())
+    }
+}
+
+// Orthonormalizes the columns of a Gaussian matrix (or its rows, when there are
+// more columns than rows) with modified Gram-Schmidt.
+fn orthogonal(shape: (usize, usize), rng: &mut dyn RngCore) -> Array2<f32> {
+    let (rows, columns) = shape;
+    let tall = (rows.max(columns), rows.min(columns));
+    let mut q: Array2<f64> = Array::random_using(tall, Normal::new(0.0, 1.0).unwrap(), rng);
+    for j in 0..tall.1 {
+        for k in 0..j {
+            let projection = q.column(j).dot(&q.column(k));
+            let previous = q.column(k).to_owned();
+            q.column_mut(j).scaled_add(-projection, &previous);
+        }
+        let norm = q.column(j).dot(&q.column(j)).sqrt().max(f64::EPSILON);
+        q.column_mut(j).mapv_inplace(|x| x / norm);
+    }
+    let q = q.mapv(|x| x as f32);
+    if rows >= columns {
+        q
+    } else {
+        q.reversed_axes()
+    }
+}
+
+impl FromStr for Initializer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Initializer::Uniform),
+            "xavier_uniform" | "glorot_uniform" => Ok(Initializer::XavierUniform),
+            "xavier_normal" | "glorot_normal" => Ok(Initializer::XavierNormal),
+            "he_uniform" => Ok(Initializer::HeUniform),
+            "he_normal" => Ok(Initializer::HeNormal),
+            "lecun" => Ok(Initializer::LeCun),
+            "orthogonal" => Ok(Initializer::Orthogonal),
+            "zeros" => Ok(Initializer::Zeros),
+            _ => match s.parse() {
+                Ok(value) => Ok(Initializer::Constant(value)),
+                Err(_) => Err(format!("unknown initializer {}", s)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn std(weights: &Array2<f32>) -> f32 {
+        weights.mapv(|w| w * w).mean().unwrap().sqrt()
+    }
+
+    #[test]
+    fn test_initializer_scales() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (fan_in, fan_out) = (200, 100);
+        let expected = [
+            (Initializer::XavierUniform, (2.0 / 300.0f32).sqrt()),
+            (Initializer::XavierNormal, (2.0 / 300.0f32).sqrt()),
+            (Initializer::HeUniform, (2.0 / 200.0f32).sqrt()),
+            (Initializer::HeNormal, (2.0 / 200.0f32).sqrt()),
+            (Initializer::LeCun, (1.0 / 200.0f32).sqrt()),
+        ];
+        for (init, target) in expected {
+            let weights = init.weights((fan_in, fan_out), fan_in, fan_out, &mut rng);
+            assert!(weights.mean().unwrap().abs() < 0.01, "{:?}", init);
+            assert!((std(&weights) / target - 1.0).abs() < 0.05, "{:?}", init);
+        }
+
+        let bias = Initializer::Constant(0.1).bias(3, &mut rng);
+        assert_eq!(bias.to_vec(), vec![0.1; 3]);
+        assert_eq!("he_normal".parse(), Ok(Initializer::HeNormal));
+        assert_eq!("0.5".parse(), Ok(Initializer::Constant(0.5)));
+    }
+
+    #[test]
+    fn test_orthogonal_columns_or_rows() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for shape in [(6, 4), (4, 6), (5, 5)] {
+            let q = Initializer::Orthogonal.weights(shape, shape.0, shape.1, &mut rng);
+            assert_eq!(q.dim(), shape);
+            let gram = if shape.0 >= shape.1 {
+                q.t().dot(&q)
+            } else {
+                q.dot(&q.t())
+            };
+            for ((i, j), &x) in gram.indexed_iter() {
+                let identity = if i == j { 1.0 } else { 0.0 };
+                assert!((x - identity).abs() < 1e-5, "{:?}", shape);
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use ndarray::{Array1, Array2, ArrayD, ArrayViewMutD, Axis};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::activation::Activation;
+use crate::conv::{Conv2D, Flatten, Pool};
+use crate::init::Initializer;
+
+// One step of the network. Every layer maps a batch of rows to a batch of rows,
+// and the network is just the layers applied in order.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Layer {
+    Dense(Dense),
+    Dropout(Dropout),
+    BatchNorm(BatchNorm),
+    Conv2D(Conv2D),
+    Pool(Pool),
+    Flatten(Flatten),
+}
+
+// What a layer remembers from its forward pass for the backward pass.
+pub enum Cache {
+    Dense {
+        z: Array2<f32>,
+    },
+    Dropout {
+        mask: Option<Array2<f32>>,
+    },
+    BatchNorm {
+        normalized: Array2<f32>,
+        inv_std: Array1<f32>,
+        batch: Option<(Array1<f32>, Array1<f32>)>,
+    },
+    Conv {
+        cols: Array2<f32>,
+        z: Array2<f32>,
+    },
+    Pool {
+        winners: Option<Array2<usize>>,
+    },
+    Flatten,
+}
+
+impl Layer {
+    // Runs the layer on a batch. `rng` is given in training mode only, which is
+    // when dropout is applied and batch normalization uses the batch statistics.
+    pub fn forward(
+        &self,
+        input: &Array2<f32>,
+        rng: Option<&mut (dyn RngCore + '_)>,
+    ) -> (Array2<f32>, Cache) {
+        match self {
+            Layer::Dense(dense) => dense.forward(input),
+            Layer::Dropout(dropout) => dropout.forward(input, rng),
+            Layer::BatchNorm(norm) => norm.forward(input, rng.is_some()),
+            Layer::Conv2D(conv) => conv.forward(input),
+            Layer::Pool(pool) => pool.forward(input),
+            Layer::Flatten(_) => (input.clone(), Cache::Flatten),
+        }
+    }
+
+    // Given the gradient of the loss with respect to this layer's output, returns
+    // the gradient with respect to its input and the (summed over the batch)
+    // gradients of its parameters, in the order of `parameters_mut`.
+    pub fn backward(
+        &self,
+        input: &Array2<f32>,
+        output: &Array2<f32>,
+        cache: &Cache,
+        grad: &Array2<f32>,
+    ) -> (Array2<f32>, Vec<ArrayD<f32>>) {
+        match (self, cache) {
+            (Layer::Dense(dense), Cache::Dense { z }) => {
+                let delta = dense.activation.backward(z, output, grad);
+                dense.backward_delta(input, &delta)
+            }
+            (Layer::Dropout(_), Cache::Dropout { mask }) => {
+                let grad_input = match mask {
+                    Some(mask) => grad * mask,
+                    None => grad.clone(),
+                };
+                (grad_input, Vec::new())
+            }
+            (
+                Layer::BatchNorm(norm),
+                Cache::BatchNorm {
+                    normalized,
+                    inv_std,
+                    batch,
+                },
+            ) => norm.backward(normalized, inv_std, batch.is_some(), grad),
+            (Layer::Conv2D(conv), Cache::Conv { cols, z }) => conv.backward(cols, z, output, grad),
+            (Layer::Pool(pool), Cache::Pool { winners }) => {
+                (pool.backward(winners.as_ref(), grad), Vec::new())
+            }
+            (Layer::Flatten(_), Cache::Flatten) => (grad.clone(), Vec::new()),
+            _ => panic!("cache does not belong to this layer"),
+        }
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<ArrayViewMutD<'_, f32>> {
+        match self {
+            Layer::Dense(dense) => {
+                let mut params = vec![dense.weights.view_mut().into_dyn()];
+                if dense.use_bias {
+                    params.push(dense.bias.view_mut().into_dyn());
+                }
+                params
+            }
+            Layer::Dropout(_) | Layer::Pool(_) | Layer::Flatten(_) => Vec::new(),
+            Layer::BatchNorm(norm) => vec![
+                norm.gamma.view_mut().into_dyn(),
+                norm.beta.view_mut().into_dyn(),
+            ],
+            Layer::Conv2D(conv) => vec![
+                conv.weights.view_mut().into_dyn(),
+                conv.bias.view_mut().into_dyn(),
+            ],
+        }
+    }
+
+    // Input and output width, for layers that fix them.
+    pub fn sizes(&self) -> Option<(usize, usize)> {
+        match self {
+            Layer::Dense(dense) => Some(dense.weights.dim()),
+            Layer::Dropout(_) => None,
+            Layer::BatchNorm(norm) => Some((norm.gamma.len(), norm.gamma.len())),
+            Layer::Conv2D(conv) => Some((conv.input.size(), conv.output().size())),
+            Layer::Pool(pool) => Some((pool.input.size(), pool.output().size())),
+            Layer::Flatten(flatten) => Some((flatten.input.size(), flatten.input.size())),
+        }
+    }
+
+    // The weight matrix of dense and convolutional layers, which is what the L1/L2
+    // penalties apply to. It is always the first entry of `parameters_mut`.
+    pub fn weights(&self) -> Option<&Array2<f32>> {
+        match self {
+            Layer::Dense(dense) => Some(&dense.weights),
+            Layer::Conv2D(conv) => Some(&conv.weights),
+            _ => None,
+        }
+    }
+
+    // Draws fresh weights from `rng` and resets biases and batch normalization to
+    // their starting values.
+    pub fn initialize(&mut self, rng: &mut dyn RngCore) {
+        match self {
+            Layer::Dense(dense) => dense.initialize(rng),
+            Layer::Conv2D(conv) => conv.initialize(rng),
+            Layer::BatchNorm(norm) => *norm = BatchNorm::new(norm.gamma.len()),
+            Layer::Dropout(_) | Layer::Pool(_) | Layer::Flatten(_) => {}
+        }
+    }
+
+    // The (weight, bias) initializers of dense and convolutional layers.
+    pub fn initializers_mut(&mut self) -> Option<(&mut Initializer, &mut Initializer)> {
+        match self {
+            Layer::Dense(dense) => Some((&mut dense.weight_init, &mut dense.bias_init)),
+            Layer::Conv2D(conv) => Some((&mut conv.weight_init, &mut conv.bias_init)),
+            _ => None,
+        }
+    }
+
+    pub fn as_dense(&self) -> Option<&Dense> {
+        match self {
+            Layer::Dense(dense) => Some(dense),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Dense {
+    pub weights: Array2<f32>,
+    pub bias: Array1<f32>,
+    pub activation: Activation,
+    pub use_bias: bool,
+    pub weight_init: Initializer,
+    pub bias_init: Initializer,
+}
+
+impl Dense {
+    pub fn new(
+        input_size: usize,
+        output_size: usize,
+        activation: Activation,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        let mut dense = Dense {
+            weights: Array2::zeros((input_size, output_size)),
+            bias: Array1::zeros(output_size),
+            activation,
+            use_bias: true,
+            weight_init: Initializer::Uniform,
+            bias_init: Initializer::Zeros,
+        };
+        dense.initialize(rng);
+        dense
+    }
+
+    pub fn with_initializers(
+        mut self,
+        weights: Initializer,
+        bias: Initializer,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        self.weight_init = weights;
+        self.bias_init = bias;
+        self.initialize(rng);
+        self
+    }
+
+    fn initialize(&mut self, rng: &mut dyn RngCore) {
+        let (input_size, output_size) = self.weights.dim();
+        self.weights = self
+            .weight_init
+            .weights(self.weights.dim(), input_size, output_size, rng);
+        self.bias = if self.use_bias {
+            self.bias_init.bias(output_size, rng)
+        } else {
+            Array1::zeros(output_size)
+        };
+    }
+
+    fn forward(&self, input: &Array2<f32>) -> (Array2<f32>, Cache) {
+        let z = input.dot(&self.weights) + &self.bias;
+        (self.activation.forward(&z), Cache::Dense { z })
+    }
+
+    // Backward pass from the gradient with respect to the pre-activation, which is
+    // where the loss hands over when it can fuse with the output activation.
+    pub fn backward_delta(
+        &self,
+        input: &Array2<f32>,
+        delta: &Array2<f32>,
+    ) -> (Array2<f32>, Vec<ArrayD<f32>>) {
+        let mut grads = vec![input.t().dot(delta).into_dyn()];
+        if self.use_bias {
+            grads.push(delta.sum_axis(Axis(0)).into_dyn());
+        }
+        (delta.dot(&self.weights.t()), grads)
+    }
+}
+
+// Inverted dropout: in training, each input is zeroed with probability `rate` and
+// the survivors are scaled up by 1 / (1 - rate), so nothing changes at inference.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Dropout {
+    pub rate: f32,
+}
+
+impl Dropout {
+    pub fn new(rate: f32) -> Self {
+        assert!((0.0..1.0).contains(&rate), "dropout rate must be in [0, 1)");
+        Dropout { rate }
+    }
+
+    fn forward(
+        &self,
+        input: &Array2<f32>,
+        rng: Option<&mut (dyn RngCore + '_)>,
+    ) -> (Array2<f32>, Cache) {
+        match rng {
+            Some(rng) if self.rate > 0.0 => {
+                let keep = 1.0 - self.rate;
+                let mask = input.mapv(|_| {
+                    if rng.gen::<f32>() < keep {
+                        1.0 / keep
+                    } else {
+                        0.0
+                    }
+                });
+                (input * &mask, Cache::Dropout { mask: Some(mask) })
+            }
+            _ => (input.clone(), Cache::Dropout { mask: None }),
+        }
+    }
+}
+
+// Normalizes every feature to zero mean and unit variance over the batch, then
+// scales and shifts it by the learned `gamma` and `beta`. Inference uses running
+// averages of the batch statistics seen in training instead.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BatchNorm {
+    pub gamma: Array1<f32>,
+    pub beta: Array1<f32>,
+    pub running_mean: Array1<f32>,
+    pub running_var: Array1<f32>,
+    pub momentum: f32,
+    pub epsilon: f32,
+}
+
+impl BatchNorm {
+    pub fn new(size: usize) -> Self {
+        BatchNorm {
+            gamma: Array1::ones(size),
+            beta: Array1::zeros(size),
+            running_mean: Array1::zeros(size),
+            running_var: Array1::ones(size),
+            momentum: 0.9,
+            epsilon: 1e-5,
+        }
+    }
+
+    fn forward(&self, input: &Array2<f32>, training: bool) -> (Array2<f32>, Cache) {
+        let (mean, var, batch) = if training {
+            let mean = input.mean_axis(Axis(0)).unwrap();
+            let var = (input - &mean).mapv(|d| d * d).mean_axis(Axis(0)).unwrap();
+            (mean.clone(), var.clone(), Some((mean, var)))
+        } else {
+            (self.running_mean.clone(), self.running_var.clone(), None)
+        };
+        let inv_std = var.mapv(|v| 1.0 / (v + self.epsilon).sqrt());
+        let normalized = (input - &mean) * &inv_std;
+        let output = &normalized * &self.gamma + &self.beta;
+        (
+            output,
+            Cache::BatchNorm {
+                normalized,
+                inv_std,
+                batch,
+            },
+        )
+    }
+
+    fn backward(
+        &self,
+        normalized: &Array2<f32>,
+        inv_std: &Array1<f32>,
+        batch_statistics: bool,
+        grad: &Array2<f32>,
+    ) -> (Array2<f32>, Vec<ArrayD<f32>>) {
+        let grad_gamma = (grad * normalized).sum_axis(Axis(0));
+        let grad_beta = grad.sum_axis(Axis(0));
+        let grad_normalized = grad * &self.gamma;
+
+        let grad_input = if batch_statistics {
+            // The batch mean and variance depend on every row, which adds the two
+            // correction terms.
+            let n = grad.nrows() as f32;
+            let sum = grad_normalized.sum_axis(Axis(0));
+            let dot = (&grad_normalized * normalized).sum_axis(Axis(0));
+            (grad_normalized * n - &sum - normalized * &dot) * &(inv_std / n)
+        } else {
+            grad_normalized * inv_std
+        };
+        (
+            grad_input,
+            vec![grad_gamma.into_dyn(), grad_beta.into_dyn()],
+        )
+    }
+
+    pub(crate) fn update_running_statistics(&mut self, mean: &Array1<f32>, var: &Array1<f32>) {
+        let m = self.momentum;
+        self.running_mean = &self.running_mean * m + &(mean * (1.0 - m));
+        self.running_var = &self.running_var * m + &(var * (1.0 - m));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_dropout_only_in_training() {
+        let dropout = Layer::Dropout(Dropout::new(0.5));
+        let input = Array2::ones((50, 40));
+
+        let (output, _) = dropout.forward(&input, None);
+        assert_eq!(output, input);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let (output, _) = dropout.forward(&input, Some(&mut rng));
+        let dropped = output.iter().filter(|&&x| x == 0.0).count();
+        assert!(dropped > 800 && dropped < 1200);
+        assert!(output.iter().all(|&x| x == 0.0 || x == 2.0));
+    }
+
+    #[test]
+    fn test_batch_norm_normalizes_the_batch() {
+        let norm = BatchNorm::new(2);
+        let input =
+            Array2::from_shape_vec((4, 2), vec![1.0, 10.0, 2.0, 20.0, 3.0, 30.0, 4.0, 40.0])
+                .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let (output, _) = Layer::BatchNorm(norm.clone()).forward(&input, Some(&mut rng));
+
+        for column in output.columns() {
+            assert!(column.mean().unwrap().abs() < 1e-5);
+            assert!((column.mapv(|x| x * x).mean().unwrap() - 1.0).abs() < 1e-3);
+        }
+
+        // Fresh running statistics are the identity transform at inference.
+        let (output, _) = Layer::BatchNorm(norm).forward(&input, None);
+        for (x, y) in output.iter().zip(input.iter()) {
+            assert!((x - y).abs() < 1e-3);
+        }
+    }
+}
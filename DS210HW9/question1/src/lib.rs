@@ -0,0 +1,17 @@
+pub mod activation;
+pub mod augment;
+pub mod conv;
+pub mod data;
+pub mod dataset;
+pub mod evaluate;
+pub mod gradcheck;
+pub mod init;
+pub mod layer;
+pub mod loss;
+pub mod metrics;
+pub mod model;
+pub mod network;
+pub mod optimizer;
+pub mod parallel;
+pub mod search;
+pub mod train;
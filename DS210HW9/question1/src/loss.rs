@@ -0,0 +1,119 @@
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::activation::Activation;
+
+// Keeps log() and the divisions in the gradients finite when an output saturates.
+const EPSILON: f32 = 1e-7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Loss {
+    Mse,
+    BinaryCrossEntropy,
+    SoftmaxCrossEntropy,
+}
+
+impl Loss {
+    // Mean loss per sample over the rows of the batch.
+    pub fn value(&self, output: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        let total: f32 = match self {
+            Loss::Mse => 0.5 * (output - target).mapv(|d| d * d).sum(),
+            Loss::BinaryCrossEntropy => output
+                .iter()
+                .zip(target.iter())
+                .map(|(&a, &t)| {
+                    let a = a.clamp(EPSILON, 1.0 - EPSILON);
+                    -(t * a.ln() + (1.0 - t) * (1.0 - a).ln())
+                })
+                .sum(),
+            Loss::SoftmaxCrossEntropy => output
+                .iter()
+                .zip(target.iter())
+                .map(|(&a, &t)| -t * a.max(EPSILON).ln())
+                .sum(),
+        };
+        total / output.nrows() as f32
+    }
+
+    // Gradient of each sample's loss with respect to the network output.
+    pub fn gradient(&self, output: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        match self {
+            Loss::Mse => output - target,
+            Loss::BinaryCrossEntropy => {
+                let mut grad = output - target;
+                grad.zip_mut_with(output, |g, &a| {
+                    let a = a.clamp(EPSILON, 1.0 - EPSILON);
+                    *g /= a * (1.0 - a);
+                });
+                grad
+            }
+            Loss::SoftmaxCrossEntropy => {
+                let mut grad = -target;
+                grad.zip_mut_with(output, |g, &a| *g /= a.max(EPSILON));
+                grad
+            }
+        }
+    }
+
+    // For the matching output activation the gradient with respect to the
+    // pre-activation collapses to `output - target`, which avoids dividing by
+    // saturated outputs. Returns None when no such shortcut applies.
+    pub fn fused_delta(
+        &self,
+        activation: Activation,
+        output: &Array2<f32>,
+        target: &Array2<f32>,
+    ) -> Option<Array2<f32>> {
+        match (self, activation) {
+            (Loss::SoftmaxCrossEntropy, Activation::Softmax)
+            | (Loss::BinaryCrossEntropy, Activation::Sigmoid) => Some(output - target),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Loss {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mse" => Ok(Loss::Mse),
+            "bce" | "binary_cross_entropy" => Ok(Loss::BinaryCrossEntropy),
+            "cross_entropy" | "softmax_cross_entropy" => Ok(Loss::SoftmaxCrossEntropy),
+            _ => Err(format!("unknown loss {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loss_values() {
+        let output = Array2::from_shape_vec((2, 2), vec![0.5, 0.5, 0.9, 0.1]).unwrap();
+        let target = Array2::from_shape_vec((2, 2), vec![1.0, 0.0, 1.0, 0.0]).unwrap();
+
+        assert!((Loss::Mse.value(&output, &target) - 0.13).abs() < 1e-6);
+        let ce = Loss::SoftmaxCrossEntropy.value(&output, &target);
+        assert!((ce - (-(0.5f32.ln()) - 0.9f32.ln()) / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fused_delta_matches_chain_rule() {
+        let z = Array2::from_shape_vec((1, 3), vec![0.2, -1.0, 2.0]).unwrap();
+        let target = Array2::from_shape_vec((1, 3), vec![0.0, 1.0, 0.0]).unwrap();
+        let a = Activation::Softmax.forward(&z);
+
+        let grad = Loss::SoftmaxCrossEntropy.gradient(&a, &target);
+        let chained = Activation::Softmax.backward(&z, &a, &grad);
+        let fused = Loss::SoftmaxCrossEntropy
+            .fused_delta(Activation::Softmax, &a, &target)
+            .unwrap();
+
+        for (x, y) in chained.iter().zip(fused.iter()) {
+            assert!((x - y).abs() < 1e-5);
+        }
+    }
+}
@@ -1,206 +1,342 @@
-use csv::ReaderBuilder;
-use ndarray::{Array, Array2};
-use ndarray_rand::rand_distr::Uniform;
-use ndarray_rand::RandomExt;
-use serde::Deserialize;
-use std::{error::Error, fs::File};
-
-struct NeuralNetwork {
-    learning_rate: f32,
-    weights_input_to_layer1: Array2<f32>,
-    weights_layer1_to_layer2: Array2<f32>,
-    weights_layer2_to_output: Array2<f32>,
-}
-
-#[derive(Debug, Deserialize)]
-struct MnistData {
-    label: u8,
-    pixels: Vec<u8>,
+use neural_network::activation::Activation;
+use neural_network::augment::Augmentation;
+use neural_network::conv::{Conv2D, Flatten, Pool, Shape};
+use neural_network::data::{load_examples, train_validation_split, IMAGE_SIDE};
+use neural_network::evaluate::Evaluation;
+use neural_network::init::Initializer;
+use neural_network::layer::Layer;
+use neural_network::loss::Loss;
+use neural_network::metrics::MetricsLog;
+use neural_network::network::NeuralNetwork;
+use neural_network::optimizer::{self, Optimizer, Schedule};
+use neural_network::train::{EpochStats, TrainConfig};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::io::{self, IsTerminal};
+use std::{env, error::Error, thread};
+
+struct Options {
+    hidden: Vec<usize>,
+    conv: bool,
+    use_bias: bool,
+    dropout: f32,
+    batch_norm: bool,
+    l1: f32,
+    l2: f32,
+    activation: Activation,
+    loss: Loss,
+    optimizer: String,
+    learning_rate: Option<f32>,
+    weight_decay: f32,
+    schedule: String,
+    epochs: usize,
+    validation: f32,
+    patience: Option<usize>,
+    resume: Option<String>,
+    save: Option<String>,
+    idx: Option<String>,
+    seed: Option<u64>,
+    init: Option<Initializer>,
+    threads: usize,
+    metrics: Option<String>,
+    augment: Augmentation,
 }
 
-fn load_mnist_data(file_path: &str) -> Result<(Array2<f32>, Array2<f32>), Box<dyn Error>> {
-    let file = File::open(file_path)?;
-    let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(file);
-
-    let mut features = Vec::new();
-    let mut labels = Vec::new();
-
-    for result in rdr.deserialize() {
-        let record: MnistData = result?;
-
-        let mut one_hot = vec![0.0; 10];
-        one_hot[record.label as usize] = 1.0;
-        labels.push(one_hot);
-
-        let normalized_pixels: Vec<f32> = record.pixels.iter().map(|&x| x as f32 / 255.0).collect();
-        features.push(normalized_pixels);
+// Hidden layer sizes are given as bare numbers, e.g. `cargo run -- 128 64 32`.
+// Flags:
+//   --conv                            two convolution and max pooling stages in
+//                                     front of the dense layers
+//   --no-bias                         train without bias terms, for comparison
+//   --dropout <rate>                  dropout after every hidden layer while training
+//   --batch-norm                      batch normalization after every hidden layer
+//   --l1 <strength>, --l2 <strength>  weight penalties added to the loss
+//   --activation <name>               hidden layer activation (sigmoid by default)
+//   --loss <mse|bce|cross_entropy>    cross-entropy also switches the output to softmax
+//   --optimizer <name>                sgd, momentum, nesterov, rmsprop or adam
+//   --learning-rate <rate>            defaults depend on the optimizer; plain sgd
+//                                     averages over each batch, so its default is
+//                                     larger than the per-sample 0.2
+//   --weight-decay <decay>            decoupled weight decay of the weight matrices
+//                                     (0 by default)
+//   --schedule <name>                 constant, step, exponential or cosine
+//   --epochs <n>                      maximum number of epochs (3 by default)
+//   --validation <fraction>           share of the training set held out to pick the
+//                                     best epoch (0.1 by default)
+//   --patience <n>                    stop after n epochs without a better
+//                                     validation loss
+//   --resume <path>                   keep training a saved model (its architecture,
+//                                     loss and optimizer replace the flags above)
+//   --save <path>                     save the trained model
+//   --idx <dir>                       read the original MNIST IDX files from dir
+//                                     instead of the CSVs
+//   --init <name>                     weight initializer for every layer: uniform,
+//                                     xavier_uniform, xavier_normal, he_uniform,
+//                                     he_normal, lecun or orthogonal
+//   --threads <n>                     threads to share each batch between (all cores
+//                                     by default)
+//   --metrics <path>                  write the per-epoch loss, accuracy, learning
+//                                     rate, time and gradient norm as CSV, or as
+//                                     JSON if the path ends in .json
+//   --shift <pixels>                  shift training images by up to this much
+//   --rotate <degrees>                rotate training images by up to this much
+//   --elastic <alpha>                 elastic distortion of training images with
+//                                     strength alpha (try 8)
+//   --noise <std>                     Gaussian noise added to training pixels
+//   --seed <n>                        seed weight initialization, the validation
+//                                     split, shuffling and dropout, so runs repeat
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        hidden: Vec::new(),
+        conv: false,
+        use_bias: true,
+        dropout: 0.0,
+        batch_norm: false,
+        l1: 0.0,
+        l2: 0.0,
+        activation: Activation::Sigmoid,
+        loss: Loss::Mse,
+        optimizer: "sgd".to_string(),
+        learning_rate: None,
+        weight_decay: 0.0,
+        schedule: "constant".to_string(),
+        epochs: 3,
+        validation: 0.1,
+        patience: None,
+        resume: None,
+        save: None,
+        idx: None,
+        seed: None,
+        init: None,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        metrics: None,
+        augment: Augmentation::default(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--conv" => options.conv = true,
+            "--no-bias" => options.use_bias = false,
+            "--dropout" => options.dropout = value()?.parse()?,
+            "--batch-norm" => options.batch_norm = true,
+            "--l1" => options.l1 = value()?.parse()?,
+            "--l2" => options.l2 = value()?.parse()?,
+            "--activation" => options.activation = value()?.parse()?,
+            "--loss" => options.loss = value()?.parse()?,
+            "--optimizer" => options.optimizer = value()?,
+            "--learning-rate" => options.learning_rate = Some(value()?.parse()?),
+            "--weight-decay" => options.weight_decay = value()?.parse()?,
+            "--schedule" => options.schedule = value()?,
+            "--epochs" => options.epochs = value()?.parse()?,
+            "--validation" => options.validation = value()?.parse()?,
+            "--patience" => options.patience = Some(value()?.parse()?),
+            "--resume" => options.resume = Some(value()?),
+            "--save" => options.save = Some(value()?),
+            "--idx" => options.idx = Some(value()?),
+            "--init" => options.init = Some(value()?.parse()?),
+            "--threads" => options.threads = value()?.parse()?,
+            "--metrics" => options.metrics = Some(value()?),
+            "--shift" => options.augment.shift = value()?.parse()?,
+            "--rotate" => options.augment.rotation = value()?.parse()?,
+            "--elastic" => options.augment.elastic_alpha = value()?.parse()?,
+            "--noise" => options.augment.noise = value()?.parse()?,
+            "--seed" => options.seed = Some(value()?.parse()?),
+            _ => options.hidden.push(arg.parse()?),
+        }
     }
-
-    let features_array = Array2::from_shape_vec((features.len(), 784), features.concat())?;
-    let labels_array = Array2::from_shape_vec((labels.len(), 10), labels.concat())?;
-
-    Ok((features_array, labels_array))
+    if options.hidden.is_empty() {
+        options.hidden = vec![512, 256];
+    }
+    Ok(options)
 }
 
-fn sigmoid(x: f32) -> f32 {
-    1.0 / (1.0 + (-x).exp())
+fn build_optimizer(options: &Options) -> Result<Box<dyn Optimizer>, Box<dyn Error>> {
+    let default_rate = match options.optimizer.as_str() {
+        "sgd" => 3.0,
+        "momentum" | "nesterov" => 0.3,
+        _ => 0.001,
+    };
+    let rate = options.learning_rate.unwrap_or(default_rate);
+    Ok(optimizer::by_name(
+        &options.optimizer,
+        rate,
+        options.weight_decay,
+    )?)
 }
 
-fn sigmoid_derivative(x: f32) -> f32 {
-    x * (1.0 - x)
+fn build_schedule(options: &Options) -> Result<Schedule, Box<dyn Error>> {
+    match options.schedule.as_str() {
+        "constant" => Ok(Schedule::Constant),
+        "step" => Ok(Schedule::Step {
+            every: 1,
+            gamma: 0.5,
+        }),
+        "exponential" => Ok(Schedule::Exponential { gamma: 0.8 }),
+        "cosine" => Ok(Schedule::Cosine {
+            epochs: options.epochs,
+            min_rate: 0.0,
+        }),
+        other => Err(format!("unknown schedule: {}", other).into()),
+    }
 }
 
-impl NeuralNetwork {
-    fn new(
-        input_size: usize,
-        layer1_size: usize,
-        layer2_size: usize,
-        output_size: usize,
-        learning_rate: f32,
-    ) -> Self {
-        let scale1 = (1.0 / (input_size + layer1_size) as f32).sqrt();
-        let scale2 = (1.0 / (layer1_size + layer2_size) as f32).sqrt();
-        let scale3 = (1.0 / (layer2_size + output_size) as f32).sqrt();
-
-        let weights_input_to_layer1 =
-            Array::random((input_size, layer1_size), Uniform::new(-scale1, scale1));
-        let weights_layer1_to_layer2 =
-            Array::random((layer1_size, layer2_size), Uniform::new(-scale2, scale2));
-        let weights_layer2_to_output =
-            Array::random((layer2_size, output_size), Uniform::new(-scale3, scale3));
+// 1x28x28 -> 8x28x28 -> 8x14x14 -> 16x14x14 -> 16x7x7, returned with the number
+// of features the stack ends in.
+fn conv_layers(activation: Activation, rng: &mut dyn RngCore) -> (Vec<Layer>, usize) {
+    let first =
+        Conv2D::new(Shape::new(1, IMAGE_SIDE, IMAGE_SIDE), 8, 3, activation, rng).with_padding(1);
+    let first_pool = Pool::max(first.output(), 2);
+    let second = Conv2D::new(first_pool.output(), 16, 3, activation, rng).with_padding(1);
+    let second_pool = Pool::max(second.output(), 2);
+    let flatten = Flatten::new(second_pool.output());
+    let features = flatten.input.size();
+    let layers = vec![
+        Layer::Conv2D(first),
+        Layer::Pool(first_pool),
+        Layer::Conv2D(second),
+        Layer::Pool(second_pool),
+        Layer::Flatten(flatten),
+    ];
+    (layers, features)
+}
 
-        NeuralNetwork {
-            learning_rate,
-            weights_input_to_layer1,
-            weights_layer1_to_layer2,
-            weights_layer2_to_output,
+// A network for `inputs` features and `classes` classes, as found in the data.
+fn build_network(
+    options: &Options,
+    inputs: usize,
+    classes: usize,
+    rng: &mut dyn RngCore,
+) -> Result<NeuralNetwork, Box<dyn Error>> {
+    let conv = if options.conv {
+        if inputs != IMAGE_SIDE * IMAGE_SIDE {
+            return Err(format!(
+                "--conv needs {0}x{0} images, but the data has {1} features",
+                IMAGE_SIDE, inputs
+            )
+            .into());
         }
+        Some(conv_layers(options.activation, rng))
+    } else {
+        None
+    };
+    let mut sizes = vec![conv.as_ref().map_or(inputs, |(_, features)| *features)];
+    sizes.extend(&options.hidden);
+    sizes.push(classes);
+
+    let mut activations = vec![options.activation; options.hidden.len()];
+    activations.push(match options.loss {
+        Loss::SoftmaxCrossEntropy => Activation::Softmax,
+        _ => Activation::Sigmoid,
+    });
+    let mut network = NeuralNetwork::new(&sizes, 0.0)
+        .with_activations(&activations)
+        .with_loss(options.loss)
+        .with_penalty(options.l1, options.l2)
+        .with_optimizer(build_optimizer(options)?);
+    if !options.use_bias {
+        network = network.without_bias();
     }
-
-    fn forward(&self, input: &Array2<f32>) -> (Array2<f32>, Array2<f32>, Array2<f32>) {
-        let z1 = input.dot(&self.weights_input_to_layer1);
-        let a1 = z1.mapv(sigmoid);
-
-        let z2 = a1.dot(&self.weights_layer1_to_layer2);
-        let a2 = z2.mapv(sigmoid);
-
-        let z3 = a2.dot(&self.weights_layer2_to_output);
-        let a3 = z3.mapv(sigmoid);
-
-        (a1, a2, a3)
+    if options.batch_norm {
+        network = network.with_batch_norm();
     }
-
-    fn backward(
-        &mut self,
-        input: &Array2<f32>,
-        layer1_output: &Array2<f32>,
-        layer2_output: &Array2<f32>,
-        final_output: &Array2<f32>,
-        target: &Array2<f32>,
-    ) {
-        let error = target - final_output;
-
-        let delta3 = error * final_output.mapv(sigmoid_derivative);
-
-        let delta2 =
-            delta3.dot(&self.weights_layer2_to_output.t()) * layer2_output.mapv(sigmoid_derivative);
-
-        let delta1 =
-            delta2.dot(&self.weights_layer1_to_layer2.t()) * layer1_output.mapv(sigmoid_derivative);
-
-        self.weights_layer2_to_output += &(self.learning_rate * layer2_output.t().dot(&delta3));
-        self.weights_layer1_to_layer2 += &(self.learning_rate * layer1_output.t().dot(&delta2));
-        self.weights_input_to_layer1 += &(self.learning_rate * input.t().dot(&delta1));
+    if options.dropout > 0.0 {
+        network = network.with_dropout(options.dropout);
+    }
+    if let Some((layers, _)) = conv {
+        network = network.with_input_layers(layers);
+    }
+    if let Some(init) = options.init {
+        network = network.with_initializer(init);
     }
+    if let Some(seed) = options.seed {
+        network = network.with_seed(seed);
+    }
+    Ok(network)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let (train_features, train_labels) = load_mnist_data("../MNIST_CSV/mnist_train.csv")?;
-    let (test_features, test_labels) = load_mnist_data("../MNIST_CSV/mnist_test.csv")?;
-
-    let mut network = NeuralNetwork::new(
-        784, //input size
-        512, //hiddenlayer1 size
-        256, //hidden layer2 size
-        10,  //output size
-        0.2, //learning rate
-    );
-
-    for epoch in 0..3 {
-        let mut correct = 0;
-        let total = train_features.nrows();
-
-        for i in 0..total {
-            let input = train_features.row(i).into_owned().into_shape((1, 784))?;
-            let target = train_labels.row(i).into_owned().into_shape((1, 10))?;
-
-            let (layer1_output, layer2_output, final_output) = network.forward(&input);
-
-            network.backward(
-                &input,
-                &layer1_output,
-                &layer2_output,
-                &final_output,
-                &target,
-            );
-
-            let predicted = final_output
-                .row(0)
-                .iter()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                .map(|(i, _)| i)
-                .unwrap();
-            let actual = target
-                .row(0)
-                .iter()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                .map(|(i, _)| i)
-                .unwrap();
-
-            if predicted == actual {
-                correct += 1;
+    let options = parse_options()?;
+    let (train_features, train_labels) = load_examples(options.idx.as_deref(), "train", None)?;
+    let (inputs, classes) = (train_features.ncols(), train_labels.ncols());
+    let (test_features, test_labels) =
+        load_examples(options.idx.as_deref(), "t10k", Some((inputs, classes)))?;
+
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let ((train_features, train_labels), (validation_features, validation_labels)) =
+        train_validation_split(&train_features, &train_labels, options.validation, &mut rng);
+
+    let mut network = match &options.resume {
+        Some(path) => {
+            let mut network = NeuralNetwork::load(path)?;
+            if let Some(seed) = options.seed {
+                network.reseed(seed);
             }
+            network
         }
+        None => build_network(&options, inputs, classes, &mut rng)?,
+    };
 
-        let epoch_accuracy = correct as f32 / total as f32 * 100.0;
-        println!(
-            "Epoch {} training accuracy: {:.2}%",
-            epoch + 1,
-            epoch_accuracy
+    let augment = (options.augment != Augmentation::default()).then_some(options.augment);
+    if let Some(augmentation) = &augment {
+        augmentation.check(inputs)?;
+    }
+    let config = TrainConfig {
+        epochs: options.epochs,
+        batch_size: 32,
+        shuffle: true,
+        schedule: build_schedule(&options)?,
+        patience: options.patience,
+        restore_best: true,
+        threads: options.threads,
+        progress: io::stderr().is_terminal(),
+        augment,
+    };
+    let on_epoch = |stats: &EpochStats| {
+        print!(
+            "Epoch {} ({:.1}s) training loss: {:.4}, accuracy: {:.2}%, learning rate: {}",
+            stats.epoch, stats.seconds, stats.loss, stats.accuracy, stats.learning_rate
         );
+        match (stats.validation_loss, stats.validation_accuracy) {
+            (Some(loss), Some(accuracy)) => {
+                println!(", validation loss: {:.4}, accuracy: {:.2}%", loss, accuracy)
+            }
+            _ => println!(),
+        }
+    };
+    let history = if validation_features.nrows() > 0 {
+        network.fit_with_validation(
+            &train_features,
+            &train_labels,
+            (&validation_features, &validation_labels),
+            &config,
+            on_epoch,
+        )
+    } else {
+        network.fit(&train_features, &train_labels, &config, on_epoch)
+    };
+    if let Some(path) = &options.metrics {
+        let run: Vec<String> = env::args().skip(1).collect();
+        let log = MetricsLog {
+            run: run.join(" "),
+            epochs: history,
+        };
+        log.write(path)?;
+        println!("Wrote training metrics to {}", path);
     }
 
-    let mut correct = 0;
-    for i in 0..test_features.nrows() {
-        let input = test_features.row(i).into_owned().into_shape((1, 784))?;
-        let target = test_labels.row(i).into_owned().into_shape((1, 10))?;
-
-        let (_, _, final_output) = network.forward(&input);
-
-        let predicted = final_output
-            .row(0)
-            .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .map(|(i, _)| i)
-            .unwrap();
-        let actual = target
-            .row(0)
-            .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .map(|(i, _)| i)
-            .unwrap();
+    let output = network.predict(&test_features, options.threads);
+    let evaluation = Evaluation::new(&output, &test_labels);
+    println!("{}", evaluation);
+    println!("Final test accuracy: {:.2}%", evaluation.accuracy());
 
-        if predicted == actual {
-            correct += 1;
-        }
+    if let Some(path) = &options.save {
+        network.save(path)?;
+        println!("Saved model to {}", path);
     }
 
-    let accuracy = (correct as f32) / (test_features.nrows() as f32) * 100.0;
-    println!("Final test accuracy: {:.2}%", accuracy);
-
     Ok(())
 }
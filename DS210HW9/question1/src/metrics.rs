@@ -0,0 +1,172 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::train::EpochStats;
+
+// The per-epoch statistics of one training run, written out as CSV (one row per
+// epoch) or JSON (with `run` describing the run) so runs can be compared later.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricsLog {
+    pub run: String,
+    pub epochs: Vec<EpochStats>,
+}
+
+impl MetricsLog {
+    pub fn new(run: &str) -> Self {
+        MetricsLog {
+            run: run.to_string(),
+            epochs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, stats: &EpochStats) {
+        self.epochs.push(stats.clone());
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for stats in &self.epochs {
+            writer.serialize(stats)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    // JSON for paths ending in .json, CSV otherwise.
+    pub fn write(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => self.write_json(path),
+            _ => self.write_csv(path),
+        }
+    }
+}
+
+// A one-line progress bar on stderr for the batches of an epoch, e.g.
+//   [=========>          ] 850/1688  loss 0.3121  ETA 0:41
+pub struct ProgressBar {
+    total: usize,
+    start: Instant,
+}
+
+const BAR_WIDTH: usize = 30;
+
+impl ProgressBar {
+    pub fn new(total: usize) -> Self {
+        ProgressBar {
+            total,
+            start: Instant::now(),
+        }
+    }
+
+    // Redraws about a hundred times over the epoch, not on every batch.
+    pub fn update(&self, done: usize, loss: f32) {
+        let step = (self.total / 100).max(1);
+        if done.is_multiple_of(step) || done == self.total {
+            eprint!("\r{}", self.line(done, loss, self.start.elapsed()));
+            let _ = io::stderr().flush();
+        }
+    }
+
+    // Clears the bar so whatever is printed next starts on a clean line.
+    pub fn finish(&self) {
+        eprint!("\r{}\r", " ".repeat(BAR_WIDTH + 48));
+        let _ = io::stderr().flush();
+    }
+
+    fn line(&self, done: usize, loss: f32, elapsed: Duration) -> String {
+        let total = self.total.max(1);
+        let filled = BAR_WIDTH * done.min(total) / total;
+        let bar = if filled < BAR_WIDTH {
+            format!(
+                "{}>{}",
+                "=".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled - 1)
+            )
+        } else {
+            "=".repeat(BAR_WIDTH)
+        };
+        let left = total - done.min(total);
+        let remaining = if done == 0 {
+            0
+        } else {
+            (elapsed.as_secs_f64() / done as f64 * left as f64).round() as u64
+        };
+        format!(
+            "[{}] {}/{}  loss {:.4}  ETA {}:{:02}",
+            bar,
+            done,
+            self.total,
+            loss,
+            remaining / 60,
+            remaining % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn stats(epoch: usize, validation: bool) -> EpochStats {
+        EpochStats {
+            epoch,
+            loss: 0.5,
+            accuracy: 90.0,
+            learning_rate: 0.1,
+            validation_loss: validation.then_some(0.25),
+            validation_accuracy: validation.then_some(95.0),
+            seconds: 1.5,
+            gradient_norm: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_write_csv_and_json() {
+        let dir = std::env::temp_dir().join(format!("nn_metrics_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut log = MetricsLog::new("128 64 --epochs 2");
+        log.record(&stats(1, false));
+        log.record(&stats(2, true));
+
+        let csv = dir.join("run.csv").to_str().unwrap().to_string();
+        log.write(&csv).unwrap();
+        assert_eq!(
+            fs::read_to_string(&csv).unwrap(),
+            "epoch,loss,accuracy,learning_rate,validation_loss,validation_accuracy,seconds,gradient_norm\n\
+             1,0.5,90.0,0.1,,,1.5,2.0\n\
+             2,0.5,90.0,0.1,0.25,95.0,1.5,2.0\n"
+        );
+
+        let json = dir.join("run.json").to_str().unwrap().to_string();
+        log.write(&json).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(value["run"], "128 64 --epochs 2");
+        assert_eq!(value["epochs"][1]["validation_accuracy"], 95.0);
+        assert!(value["epochs"][0]["validation_loss"].is_null());
+    }
+
+    #[test]
+    fn test_progress_line() {
+        let bar = ProgressBar::new(10);
+        assert_eq!(
+            bar.line(5, 0.25, Duration::from_secs(50)),
+            "[===============>              ] 5/10  loss 0.2500  ETA 0:50"
+        );
+        assert!(bar
+            .line(10, 0.25, Duration::from_secs(100))
+            .starts_with(&format!("[{}] 10/10", "=".repeat(30))));
+    }
+}
@@ -0,0 +1,150 @@
+// Saved models. A file is an 8-byte magic and a little-endian u32 version,
+// followed by the bincode encoding of `SavedModel`. Bump VERSION whenever
+// `SavedModel` (or anything it contains) changes shape.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use crate::layer::Layer;
+use crate::loss::Loss;
+use crate::network::{NeuralNetwork, Penalty};
+use crate::optimizer::OptimizerState;
+
+const MAGIC: &[u8; 8] = b"NNMODEL\0";
+const VERSION: u32 = 4;
+
+#[derive(Serialize, Deserialize)]
+struct SavedModel {
+    loss: Loss,
+    penalty: Penalty,
+    layers: Vec<Layer>,
+    optimizer: OptimizerState,
+}
+
+impl NeuralNetwork {
+    // Writes the architecture, weights and optimizer state, so a loaded network
+    // predicts exactly as this one does and can carry on training.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let model = SavedModel {
+            loss: self.loss,
+            penalty: self.penalty,
+            layers: self.layers.clone(),
+            optimizer: self.optimizer.state(),
+        };
+
+        // Write to a temporary file and rename it so a crash never leaves half a model.
+        let tmp = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &model)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<NeuralNetwork, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a saved model", path).into());
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(format!(
+                "{} has model version {}, expected {}",
+                path, version, VERSION
+            )
+            .into());
+        }
+
+        let model: SavedModel = bincode::deserialize_from(reader)?;
+        if !model.layers.iter().any(|layer| layer.as_dense().is_some()) {
+            return Err(format!("{} has no dense layers", path).into());
+        }
+        let sizes: Vec<(usize, usize)> = model.layers.iter().filter_map(Layer::sizes).collect();
+        for pair in sizes.windows(2) {
+            if pair[0].1 != pair[1].0 {
+                return Err(format!("{} has mismatched layer sizes", path).into());
+            }
+        }
+
+        Ok(NeuralNetwork {
+            optimizer: model.optimizer.into_optimizer(),
+            loss: model.loss,
+            penalty: model.penalty,
+            layers: model.layers,
+            rng: StdRng::from_entropy(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::Activation;
+    use crate::optimizer::Adam;
+    use ndarray::Array2;
+
+    fn temp_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("nn_model_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let input = Array2::from_shape_fn((4, 3), |(i, j)| (i + j) as f32 / 5.0);
+        let target = Array2::from_shape_fn((4, 2), |(i, j)| ((i + j) % 2) as f32);
+
+        let mut network = NeuralNetwork::new(&[3, 5, 2], 0.1)
+            .with_activations(&[Activation::Relu, Activation::Softmax])
+            .with_loss(Loss::SoftmaxCrossEntropy)
+            .with_batch_norm()
+            .with_penalty(0.0, 0.01)
+            .with_optimizer(Box::new(Adam::new(0.01)))
+            .with_seed(6);
+        network.train_batch(&input, &target);
+
+        let path = temp_path("round_trip.model");
+        network.save(&path).unwrap();
+        let mut loaded = NeuralNetwork::load(&path).unwrap();
+
+        assert_eq!(loaded.loss(), Loss::SoftmaxCrossEntropy);
+        assert_eq!(loaded.penalty(), network.penalty());
+        assert_eq!(
+            loaded.layers()[0].as_dense().unwrap().activation,
+            Activation::Relu
+        );
+        assert_eq!(
+            loaded.forward(&input).output(),
+            network.forward(&input).output()
+        );
+
+        // The Adam moments come back too, so the next step matches exactly.
+        network.train_batch(&input, &target);
+        loaded.train_batch(&input, &target);
+        for (a, b) in network.layers().iter().zip(loaded.layers()) {
+            if let (Layer::Dense(a), Layer::Dense(b)) = (a, b) {
+                assert_eq!(a.weights, b.weights);
+                assert_eq!(a.bias, b.bias);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_rejects_other_files() {
+        let path = temp_path("not_a_model.model");
+        fs::write(&path, b"label,pixel0\n").unwrap();
+        assert!(NeuralNetwork::load(&path).is_err());
+    }
+}
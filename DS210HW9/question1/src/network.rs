@@ -0,0 +1,578 @@
+use ndarray::{Array2, ArrayD, ArrayViewMutD};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use crate::activation::Activation;
+use crate::init::Initializer;
+use crate::layer::{BatchNorm, Cache, Dense, Dropout, Layer};
+use crate::loss::Loss;
+use crate::optimizer::{Optimizer, Sgd};
+
+// Everything the backward pass needs from a forward pass: each layer's output
+// and whatever else it cached along the way.
+pub struct ForwardPass {
+    pub outputs: Vec<Array2<f32>>,
+    pub caches: Vec<Cache>,
+}
+
+impl ForwardPass {
+    pub fn output(&self) -> &Array2<f32> {
+        self.outputs.last().unwrap()
+    }
+}
+
+// L1 and L2 weight penalties. They apply to the weights of dense and
+// convolutional layers, not to biases or batch normalization parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Penalty {
+    pub l1: f32,
+    pub l2: f32,
+}
+
+pub struct NeuralNetwork {
+    pub(crate) optimizer: Box<dyn Optimizer>,
+    pub(crate) loss: Loss,
+    pub(crate) penalty: Penalty,
+    pub(crate) layers: Vec<Layer>,
+    // Drives shuffling and dropout in training.
+    pub(crate) rng: StdRng,
+}
+
+impl NeuralNetwork {
+    // `sizes` lists the width of every layer from the input to the output, so
+    // [784, 512, 256, 10] builds two hidden layers. Every layer starts out as sigmoid,
+    // trained with plain SGD at `learning_rate`.
+    pub fn new(sizes: &[usize], learning_rate: f32) -> Self {
+        assert!(
+            sizes.len() >= 2,
+            "need at least an input and an output size"
+        );
+
+        let mut rng = StdRng::from_entropy();
+        let layers = sizes
+            .windows(2)
+            .map(|pair| Layer::Dense(Dense::new(pair[0], pair[1], Activation::Sigmoid, &mut rng)))
+            .collect();
+        NeuralNetwork {
+            rng,
+            ..NeuralNetwork::from_layers(layers, learning_rate)
+        }
+    }
+
+    // Unseeded until `with_seed`; the layers keep the weights they were built with.
+    pub fn from_layers(layers: Vec<Layer>, learning_rate: f32) -> Self {
+        assert!(
+            layers.iter().any(|layer| layer.as_dense().is_some()),
+            "need at least one dense layer"
+        );
+        NeuralNetwork {
+            optimizer: Box::new(Sgd::new(learning_rate)),
+            loss: Loss::Mse,
+            penalty: Penalty::default(),
+            layers,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    // Draws every layer's weights again from a generator seeded with `seed`, and
+    // keeps using it for shuffling and dropout, so two runs with the same seed and
+    // data train identically. Call it once all the layers are in place.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.reseed(seed);
+        for layer in &mut self.layers {
+            layer.initialize(&mut self.rng);
+        }
+        self
+    }
+
+    // Sets the weight initializer of every dense and convolutional layer and draws
+    // their weights again.
+    pub fn with_initializer(self, weights: Initializer) -> Self {
+        let count = self
+            .layers
+            .iter()
+            .filter(|layer| layer.weights().is_some())
+            .count();
+        self.with_initializers(&vec![weights; count])
+    }
+
+    // One weight initializer per dense or convolutional layer, in order, e.g. He
+    // for relu hidden layers and Xavier for a softmax output.
+    pub fn with_initializers(mut self, initializers: &[Initializer]) -> Self {
+        let rng = &mut self.rng;
+        let layers: Vec<&mut Layer> = self
+            .layers
+            .iter_mut()
+            .filter(|layer| layer.weights().is_some())
+            .collect();
+        assert_eq!(
+            initializers.len(),
+            layers.len(),
+            "need one initializer per layer with weights"
+        );
+        for (layer, initializer) in layers.into_iter().zip(initializers) {
+            *layer.initializers_mut().unwrap().0 = *initializer;
+            layer.initialize(rng);
+        }
+        self
+    }
+
+    // Sets the bias initializer of every dense and convolutional layer, which are
+    // then drawn again along with their weights.
+    pub fn with_bias_initializer(mut self, bias: Initializer) -> Self {
+        for layer in self.layers.iter_mut() {
+            if let Some((_, bias_init)) = layer.initializers_mut() {
+                *bias_init = bias;
+                layer.initialize(&mut self.rng);
+            }
+        }
+        self
+    }
+
+    // Seeds shuffling and dropout without touching the weights, e.g. to resume
+    // training a loaded model reproducibly.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // Keeps every bias at zero, which gives the original bias-free network.
+    pub fn without_bias(mut self) -> Self {
+        for layer in &mut self.layers {
+            if let Layer::Dense(dense) = layer {
+                dense.use_bias = false;
+                dense.bias.fill(0.0);
+            }
+        }
+        self
+    }
+
+    // Sets the activation of each dense layer, in order from the first hidden layer
+    // to the output layer.
+    pub fn with_activations(mut self, activations: &[Activation]) -> Self {
+        let mut dense: Vec<&mut Dense> = self
+            .layers
+            .iter_mut()
+            .filter_map(|layer| match layer {
+                Layer::Dense(dense) => Some(dense),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            activations.len(),
+            dense.len(),
+            "need one activation per layer"
+        );
+        for (layer, activation) in dense.iter_mut().zip(activations) {
+            layer.activation = *activation;
+        }
+        self
+    }
+
+    // Adds dropout after every hidden dense layer.
+    pub fn with_dropout(self, rate: f32) -> Self {
+        self.after_hidden_layers(|_| Layer::Dropout(Dropout::new(rate)))
+    }
+
+    // Adds batch normalization after every hidden dense layer, after its activation.
+    pub fn with_batch_norm(self) -> Self {
+        self.after_hidden_layers(|dense| Layer::BatchNorm(BatchNorm::new(dense.weights.ncols())))
+    }
+
+    // Inserts a layer after each hidden dense layer and whatever already follows
+    // it, i.e. just before the next dense layer.
+    fn after_hidden_layers<F: Fn(&Dense) -> Layer>(mut self, make: F) -> Self {
+        let mut layers = Vec::with_capacity(2 * self.layers.len());
+        let mut pending = None;
+        for layer in self.layers {
+            if let Layer::Dense(dense) = &layer {
+                layers.extend(pending.take());
+                pending = Some(make(dense));
+            }
+            layers.push(layer);
+        }
+        self.layers = layers;
+        self
+    }
+
+    // Puts `layers` in front of the existing ones, e.g. a convolutional feature
+    // extractor ahead of the dense layers. Their output must match the first
+    // dense layer's input.
+    pub fn with_input_layers(mut self, mut layers: Vec<Layer>) -> Self {
+        layers.append(&mut self.layers);
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_penalty(mut self, l1: f32, l2: f32) -> Self {
+        self.penalty = Penalty { l1, l2 };
+        self
+    }
+
+    pub fn with_loss(mut self, loss: Loss) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    pub fn with_optimizer(mut self, optimizer: Box<dyn Optimizer>) -> Self {
+        self.optimizer = optimizer;
+        self
+    }
+
+    pub fn optimizer(&self) -> &dyn Optimizer {
+        self.optimizer.as_ref()
+    }
+
+    pub fn optimizer_mut(&mut self) -> &mut dyn Optimizer {
+        self.optimizer.as_mut()
+    }
+
+    pub fn loss(&self) -> Loss {
+        self.loss
+    }
+
+    pub fn penalty(&self) -> Penalty {
+        self.penalty
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn output_activation(&self) -> Activation {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.as_dense())
+            .unwrap()
+            .activation
+    }
+
+    // The number of features an input row must have, from the first layer that
+    // fixes it (dropout works on any width).
+    pub fn input_size(&self) -> usize {
+        self.layers
+            .iter()
+            .find_map(|layer| match layer {
+                Layer::Dense(dense) => Some(dense.weights.nrows()),
+                Layer::BatchNorm(norm) => Some(norm.gamma.len()),
+                Layer::Conv2D(conv) => Some(conv.input.size()),
+                Layer::Pool(pool) => Some(pool.input.size()),
+                Layer::Flatten(flatten) => Some(flatten.input.size()),
+                Layer::Dropout(_) => None,
+            })
+            .unwrap()
+    }
+
+    // Inference: no dropout, and batch normalization uses its running statistics.
+    pub fn forward(&self, input: &Array2<f32>) -> ForwardPass {
+        run(&self.layers, input, None)
+    }
+
+    pub fn forward_training(&self, input: &Array2<f32>, rng: &mut dyn RngCore) -> ForwardPass {
+        run(&self.layers, input, Some(rng))
+    }
+
+    // The value of the weight penalties, to be added to the mean loss per sample.
+    pub fn penalty_value(&self) -> f32 {
+        let Penalty { l1, l2 } = self.penalty;
+        if l1 == 0.0 && l2 == 0.0 {
+            return 0.0;
+        }
+        self.layers
+            .iter()
+            .filter_map(Layer::weights)
+            .map(|weights| {
+                l1 * weights.mapv(f32::abs).sum() + 0.5 * l2 * weights.mapv(|w| w * w).sum()
+            })
+            .sum()
+    }
+
+    // Returns the gradient of the loss averaged over the batch, plus the weight
+    // penalties, one array per parameter in the order of `parameters_mut`.
+    // Nothing is updated here.
+    pub fn backward(
+        &self,
+        input: &Array2<f32>,
+        pass: &ForwardPass,
+        target: &Array2<f32>,
+    ) -> Vec<ArrayD<f32>> {
+        let last = self.layers.len() - 1;
+        let previous = |i: usize| if i == 0 { input } else { &pass.outputs[i - 1] };
+
+        // The loss hands the last layer dL/dz directly when it fuses with the
+        // output activation; otherwise it starts from dL/da.
+        let fused = self.layers[last].as_dense().and_then(|dense| {
+            self.loss
+                .fused_delta(dense.activation, pass.output(), target)
+                .map(|delta| dense.backward_delta(previous(last), &delta))
+        });
+        let (mut grad, last_grads) = match fused {
+            Some(result) => result,
+            None => self.layers[last].backward(
+                previous(last),
+                pass.output(),
+                &pass.caches[last],
+                &self.loss.gradient(pass.output(), target),
+            ),
+        };
+
+        let mut layer_grads = vec![last_grads];
+        for i in (0..last).rev() {
+            let (grad_input, grads) =
+                self.layers[i].backward(previous(i), &pass.outputs[i], &pass.caches[i], &grad);
+            layer_grads.push(grads);
+            grad = grad_input;
+        }
+        layer_grads.reverse();
+
+        // Gradients are summed over the rows of the batch, so scale by its size to
+        // average them. The penalties are not per sample, so they are added after.
+        let scale = 1.0 / input.nrows() as f32;
+        let Penalty { l1, l2 } = self.penalty;
+        let mut all = Vec::new();
+        for (layer, grads) in self.layers.iter().zip(layer_grads) {
+            for (j, mut g) in grads.into_iter().enumerate() {
+                g *= scale;
+                if let (Some(weights), 0) = (layer.weights(), j) {
+                    if l1 != 0.0 || l2 != 0.0 {
+                        // |w| has no slope at 0; take the subgradient 0 there
+                        // rather than signum's 1, so zero weights stay put.
+                        g.zip_mut_with(&weights.view().into_dyn(), |g, &w| {
+                            let sign = if w == 0.0 { 0.0 } else { w.signum() };
+                            *g += l1 * sign + l2 * w;
+                        });
+                    }
+                }
+                all.push(g);
+            }
+        }
+        all
+    }
+
+    // Every trainable parameter, layer by layer: a dense layer's weights and then
+    // its bias (unless the network was built without bias), and batch
+    // normalization's scale and shift.
+    pub fn parameters_mut(&mut self) -> Vec<ArrayViewMutD<'_, f32>> {
+        parameters(&mut self.layers)
+    }
+
+    pub fn apply_gradients(&mut self, grads: &[ArrayD<f32>]) {
+        let mut params = parameters(&mut self.layers);
+        self.optimizer.step(&mut params, grads);
+    }
+
+    // Folds the batch statistics from a training pass into the running averages
+    // that batch normalization uses at inference.
+    pub fn update_running_statistics(&mut self, pass: &ForwardPass) {
+        for (layer, cache) in self.layers.iter_mut().zip(&pass.caches) {
+            if let (
+                Layer::BatchNorm(norm),
+                Cache::BatchNorm {
+                    batch: Some((mean, var)),
+                    ..
+                },
+            ) = (layer, cache)
+            {
+                norm.update_running_statistics(mean, var);
+            }
+        }
+    }
+
+    // One optimization step on a batch. Returns the (training mode) forward pass
+    // so callers can report the loss and accuracy it was computed from.
+    pub fn train_batch(&mut self, input: &Array2<f32>, target: &Array2<f32>) -> ForwardPass {
+        let (pass, grads) = self.training_gradients(input, target);
+        self.apply_gradients(&grads);
+        pass
+    }
+
+    // The training pass and gradient for a batch, with the running statistics
+    // updated, but without the optimizer step.
+    pub(crate) fn training_gradients(
+        &mut self,
+        input: &Array2<f32>,
+        target: &Array2<f32>,
+    ) -> (ForwardPass, Vec<ArrayD<f32>>) {
+        let pass = run(&self.layers, input, Some(&mut self.rng));
+        let grads = self.backward(input, &pass, target);
+        self.update_running_statistics(&pass);
+        (pass, grads)
+    }
+}
+
+fn run(
+    layers: &[Layer],
+    input: &Array2<f32>,
+    mut rng: Option<&mut (dyn RngCore + '_)>,
+) -> ForwardPass {
+    let mut pass = ForwardPass {
+        outputs: Vec::with_capacity(layers.len()),
+        caches: Vec::with_capacity(layers.len()),
+    };
+    for layer in layers {
+        let previous = pass.outputs.last().unwrap_or(input);
+        let (output, cache) = layer.forward(previous, rng.as_deref_mut());
+        pass.outputs.push(output);
+        pass.caches.push(cache);
+    }
+    pass
+}
+
+fn parameters(layers: &mut [Layer]) -> Vec<ArrayViewMutD<'_, f32>> {
+    layers
+        .iter_mut()
+        .flat_map(|layer| layer.parameters_mut())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_shapes_follow_sizes() {
+        let network = NeuralNetwork::new(&[6, 5, 4, 3], 0.1);
+
+        let shapes: Vec<(usize, usize)> = network
+            .layers()
+            .iter()
+            .map(|l| l.as_dense().unwrap().weights.dim())
+            .collect();
+        assert_eq!(shapes, vec![(6, 5), (5, 4), (4, 3)]);
+        assert_eq!(network.input_size(), 6);
+    }
+
+    #[test]
+    fn test_forward_and_backward_for_any_depth() {
+        for hidden in 1..=6 {
+            let mut sizes = vec![4];
+            sizes.extend(std::iter::repeat_n(3, hidden));
+            sizes.push(2);
+            // Seeded: with six sigmoid layers some draws leave the first layer's
+            // gradient below f32 precision.
+            let mut network = NeuralNetwork::new(&sizes, 0.5).with_seed(1);
+
+            let input = Array2::from_elem((1, 4), 0.5);
+            let target = Array2::from_shape_vec((1, 2), vec![1.0, 0.0]).unwrap();
+            let pass = network.forward(&input);
+            assert_eq!(pass.outputs.len(), hidden + 1);
+            assert_eq!(pass.output().dim(), (1, 2));
+
+            let grads = network.backward(&input, &pass, &target);
+            assert_eq!(grads.len(), 2 * (hidden + 1));
+
+            let before = network.layers()[0].as_dense().unwrap().weights.clone();
+            network.apply_gradients(&grads);
+            assert_ne!(network.layers()[0].as_dense().unwrap().weights, before);
+        }
+    }
+
+    #[test]
+    fn test_bias_learns_an_offset() {
+        // With an all-zero input, only the bias can move the output away from 0.5.
+        let input = Array2::zeros((1, 3));
+        let target = Array2::from_elem((1, 1), 0.9);
+
+        let mut with_bias = NeuralNetwork::new(&[3, 1], 1.0).with_seed(2);
+        let mut without_bias = NeuralNetwork::new(&[3, 1], 1.0).without_bias().with_seed(2);
+        for _ in 0..500 {
+            with_bias.train_batch(&input, &target);
+            without_bias.train_batch(&input, &target);
+        }
+
+        assert!((with_bias.forward(&input).output()[[0, 0]] - 0.9).abs() < 0.01);
+        assert_eq!(without_bias.forward(&input).output()[[0, 0]], 0.5);
+    }
+
+    #[test]
+    fn test_initializers_apply_per_layer() {
+        let network = NeuralNetwork::new(&[4, 3, 2], 0.1)
+            .with_initializers(&[Initializer::Constant(0.5), Initializer::Zeros])
+            .with_bias_initializer(Initializer::Constant(0.1));
+        let first = network.layers()[0].as_dense().unwrap();
+        let second = network.layers()[1].as_dense().unwrap();
+        assert!(first.weights.iter().all(|&w| w == 0.5));
+        assert!(second.weights.iter().all(|&w| w == 0.0));
+        assert!(first.bias.iter().all(|&b| b == 0.1));
+
+        // Reseeding draws again with the same initializers.
+        let network = network.with_seed(3);
+        assert!(network.layers()[0]
+            .as_dense()
+            .unwrap()
+            .weights
+            .iter()
+            .all(|&w| w == 0.5));
+    }
+
+    #[test]
+    fn test_regularized_layers_are_placed_between_hidden_layers() {
+        let network = NeuralNetwork::new(&[4, 3, 3, 2], 0.1)
+            .with_batch_norm()
+            .with_dropout(0.2);
+        let kinds: Vec<&str> = network
+            .layers()
+            .iter()
+            .map(|layer| match layer {
+                Layer::Dense(_) => "dense",
+                Layer::Dropout(_) => "dropout",
+                Layer::BatchNorm(_) => "batch_norm",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "dense",
+                "batch_norm",
+                "dropout",
+                "dense",
+                "batch_norm",
+                "dropout",
+                "dense"
+            ]
+        );
+
+        // Inference is deterministic even with dropout in the network.
+        let input = Array2::from_elem((3, 4), 0.5);
+        assert_eq!(
+            network.forward(&input).output(),
+            network.forward(&input).output()
+        );
+    }
+
+    #[test]
+    fn test_l2_penalty_shrinks_weights() {
+        // With an output that already matches the target, only the penalty moves
+        // the weights.
+        let input = Array2::from_elem((2, 3), 1.0);
+        let mut network = NeuralNetwork::new(&[3, 1], 0.1)
+            .with_activations(&[Activation::Identity])
+            .with_penalty(0.0, 0.5);
+        let target = network.forward(&input).output().clone();
+        let before = network.penalty_value();
+
+        let pass = network.forward(&input);
+        let grads = network.backward(&input, &pass, &target);
+        let weights = &network.layers()[0].as_dense().unwrap().weights;
+        for (g, w) in grads[0].iter().zip(weights.iter()) {
+            assert!((g - 0.5 * w).abs() < 1e-6);
+        }
+        network.apply_gradients(&grads);
+        assert!(network.penalty_value() < before);
+    }
+
+    #[test]
+    fn test_l1_penalty_leaves_zero_weights_alone() {
+        let input = Array2::from_elem((2, 3), 1.0);
+        let network = NeuralNetwork::new(&[3, 1], 0.1)
+            .with_activations(&[Activation::Identity])
+            .with_initializer(Initializer::Zeros)
+            .with_penalty(0.5, 0.0);
+        let target = network.forward(&input).output().clone();
+
+        let pass = network.forward(&input);
+        let grads = network.backward(&input, &pass, &target);
+        assert!(grads[0].iter().all(|&g| g == 0.0));
+    }
+}
@@ -0,0 +1,361 @@
+use ndarray::{ArrayD, ArrayViewMutD, Zip};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Updates parameters from gradients of the loss. `params` and `grads` line up one
+// to one, in the order given by `NeuralNetwork::parameters_mut`, and the same
+// order is used on every call so optimizers can keep per-parameter state.
+pub trait Optimizer: Send + Sync {
+    fn step(&mut self, params: &mut [ArrayViewMutD<f32>], grads: &[ArrayD<f32>]);
+    fn learning_rate(&self) -> f32;
+    fn set_learning_rate(&mut self, rate: f32);
+    // A copy of the optimizer, including its per-parameter state, for saving.
+    fn state(&self) -> OptimizerState;
+}
+
+// Every optimizer in a form that can be written to disk and turned back into a
+// working optimizer, so training can pick up where it stopped.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum OptimizerState {
+    Sgd(Sgd),
+    RmsProp(RmsProp),
+    Adam(Adam),
+}
+
+impl OptimizerState {
+    pub fn into_optimizer(self) -> Box<dyn Optimizer> {
+        match self {
+            OptimizerState::Sgd(sgd) => Box::new(sgd),
+            OptimizerState::RmsProp(rmsprop) => Box::new(rmsprop),
+            OptimizerState::Adam(adam) => Box::new(adam),
+        }
+    }
+}
+
+// Builds an optimizer from its command line name: sgd, momentum, nesterov (both
+// with momentum 0.9), rmsprop or adam.
+pub fn by_name(
+    name: &str,
+    learning_rate: f32,
+    weight_decay: f32,
+) -> Result<Box<dyn Optimizer>, String> {
+    let optimizer: Box<dyn Optimizer> = match name {
+        "sgd" => Box::new(Sgd::new(learning_rate).with_weight_decay(weight_decay)),
+        "momentum" => Box::new(Sgd::momentum(learning_rate, 0.9).with_weight_decay(weight_decay)),
+        "nesterov" => Box::new(Sgd::nesterov(learning_rate, 0.9).with_weight_decay(weight_decay)),
+        "rmsprop" => Box::new(RmsProp::new(learning_rate).with_weight_decay(weight_decay)),
+        "adam" => Box::new(Adam::new(learning_rate).with_weight_decay(weight_decay)),
+        other => return Err(format!("unknown optimizer: {}", other)),
+    };
+    Ok(optimizer)
+}
+
+fn zeros_like(grads: &[ArrayD<f32>]) -> Vec<ArrayD<f32>> {
+    grads.iter().map(|g| ArrayD::zeros(g.raw_dim())).collect()
+}
+
+// Decoupled weight decay: shrinks the weight matrices towards zero by
+// `rate * decay` before the gradient step. Biases and batch normalization's scale
+// and shift are the only 1-D parameters, and are left alone.
+fn decay(params: &mut [ArrayViewMutD<f32>], rate: f32, weight_decay: f32) {
+    if weight_decay > 0.0 {
+        for param in params.iter_mut().filter(|param| param.ndim() >= 2) {
+            *param *= 1.0 - rate * weight_decay;
+        }
+    }
+}
+
+// Plain SGD, with optional (Nesterov) momentum.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sgd {
+    pub learning_rate: f32,
+    pub momentum: f32,
+    pub nesterov: bool,
+    pub weight_decay: f32,
+    velocity: Vec<ArrayD<f32>>,
+}
+
+impl Sgd {
+    pub fn new(learning_rate: f32) -> Self {
+        Sgd {
+            learning_rate,
+            momentum: 0.0,
+            nesterov: false,
+            weight_decay: 0.0,
+            velocity: Vec::new(),
+        }
+    }
+
+    pub fn momentum(learning_rate: f32, momentum: f32) -> Self {
+        Sgd {
+            momentum,
+            ..Sgd::new(learning_rate)
+        }
+    }
+
+    pub fn nesterov(learning_rate: f32, momentum: f32) -> Self {
+        Sgd {
+            nesterov: true,
+            ..Sgd::momentum(learning_rate, momentum)
+        }
+    }
+
+    pub fn with_weight_decay(mut self, weight_decay: f32) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl Optimizer for Sgd {
+    fn step(&mut self, params: &mut [ArrayViewMutD<f32>], grads: &[ArrayD<f32>]) {
+        decay(params, self.learning_rate, self.weight_decay);
+
+        if self.momentum == 0.0 {
+            for (param, grad) in params.iter_mut().zip(grads) {
+                param.scaled_add(-self.learning_rate, grad);
+            }
+            return;
+        }
+
+        if self.velocity.is_empty() {
+            self.velocity = zeros_like(grads);
+        }
+        let (rate, momentum, nesterov) = (self.learning_rate, self.momentum, self.nesterov);
+        for ((param, grad), velocity) in params.iter_mut().zip(grads).zip(&mut self.velocity) {
+            Zip::from(param)
+                .and(grad)
+                .and(velocity)
+                .for_each(|p, &g, v| {
+                    *v = momentum * *v + g;
+                    let step = if nesterov { g + momentum * *v } else { *v };
+                    *p -= rate * step;
+                });
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, rate: f32) {
+        self.learning_rate = rate;
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState::Sgd(self.clone())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RmsProp {
+    pub learning_rate: f32,
+    pub decay: f32,
+    pub epsilon: f32,
+    pub weight_decay: f32,
+    mean_square: Vec<ArrayD<f32>>,
+}
+
+impl RmsProp {
+    pub fn new(learning_rate: f32) -> Self {
+        RmsProp {
+            learning_rate,
+            decay: 0.9,
+            epsilon: 1e-8,
+            weight_decay: 0.0,
+            mean_square: Vec::new(),
+        }
+    }
+
+    pub fn with_weight_decay(mut self, weight_decay: f32) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn step(&mut self, params: &mut [ArrayViewMutD<f32>], grads: &[ArrayD<f32>]) {
+        decay(params, self.learning_rate, self.weight_decay);
+
+        if self.mean_square.is_empty() {
+            self.mean_square = zeros_like(grads);
+        }
+        let (rate, rho, eps) = (self.learning_rate, self.decay, self.epsilon);
+        for ((param, grad), ms) in params.iter_mut().zip(grads).zip(&mut self.mean_square) {
+            Zip::from(param).and(grad).and(ms).for_each(|p, &g, s| {
+                *s = rho * *s + (1.0 - rho) * g * g;
+                *p -= rate * g / (s.sqrt() + eps);
+            });
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, rate: f32) {
+        self.learning_rate = rate;
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState::RmsProp(self.clone())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Adam {
+    pub learning_rate: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub epsilon: f32,
+    pub weight_decay: f32,
+    steps: i32,
+    first_moment: Vec<ArrayD<f32>>,
+    second_moment: Vec<ArrayD<f32>>,
+}
+
+impl Adam {
+    pub fn new(learning_rate: f32) -> Self {
+        Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            weight_decay: 0.0,
+            steps: 0,
+            first_moment: Vec::new(),
+            second_moment: Vec::new(),
+        }
+    }
+
+    pub fn with_weight_decay(mut self, weight_decay: f32) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self, params: &mut [ArrayViewMutD<f32>], grads: &[ArrayD<f32>]) {
+        decay(params, self.learning_rate, self.weight_decay);
+
+        if self.first_moment.is_empty() {
+            self.first_moment = zeros_like(grads);
+            self.second_moment = zeros_like(grads);
+        }
+        self.steps += 1;
+        let (rate, b1, b2, eps) = (self.learning_rate, self.beta1, self.beta2, self.epsilon);
+        let correction1 = 1.0 - b1.powi(self.steps);
+        let correction2 = 1.0 - b2.powi(self.steps);
+
+        let moments = self.first_moment.iter_mut().zip(&mut self.second_moment);
+        for ((param, grad), (m, v)) in params.iter_mut().zip(grads).zip(moments) {
+            Zip::from(param)
+                .and(grad)
+                .and(m)
+                .and(v)
+                .for_each(|p, &g, m, v| {
+                    *m = b1 * *m + (1.0 - b1) * g;
+                    *v = b2 * *v + (1.0 - b2) * g * g;
+                    let m_hat = *m / correction1;
+                    let v_hat = *v / correction2;
+                    *p -= rate * m_hat / (v_hat.sqrt() + eps);
+                });
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, rate: f32) {
+        self.learning_rate = rate;
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState::Adam(self.clone())
+    }
+}
+
+// Learning rate as a function of the (zero-based) epoch, relative to the rate the
+// optimizer started with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Constant,
+    Step { every: usize, gamma: f32 },
+    Exponential { gamma: f32 },
+    Cosine { epochs: usize, min_rate: f32 },
+}
+
+impl Schedule {
+    pub fn rate(&self, base: f32, epoch: usize) -> f32 {
+        match *self {
+            Schedule::Constant => base,
+            Schedule::Step { every, gamma } => base * gamma.powi((epoch / every.max(1)) as i32),
+            Schedule::Exponential { gamma } => base * gamma.powi(epoch as i32),
+            Schedule::Cosine { epochs, min_rate } => {
+                let progress = epoch.min(epochs) as f32 / epochs.max(1) as f32;
+                min_rate + 0.5 * (base - min_rate) * (1.0 + (PI * progress).cos())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2, ArrayD};
+
+    // Minimises f(x) = x^2 from x = 1 and returns where the optimizer ends up.
+    fn minimise(optimizer: &mut dyn Optimizer, steps: usize) -> f32 {
+        let mut x: ArrayD<f32> = arr1(&[1.0]).into_dyn();
+        for _ in 0..steps {
+            let grad = x.mapv(|v| 2.0 * v);
+            optimizer.step(&mut [x.view_mut()], &[grad]);
+        }
+        x[[0]]
+    }
+
+    #[test]
+    fn test_optimizers_minimise_a_quadratic() {
+        let optimizers: Vec<Box<dyn Optimizer>> = vec![
+            Box::new(Sgd::new(0.1)),
+            Box::new(Sgd::momentum(0.1, 0.9)),
+            Box::new(Sgd::nesterov(0.1, 0.9)),
+            Box::new(RmsProp::new(0.01)),
+            Box::new(Adam::new(0.05)),
+        ];
+        for mut optimizer in optimizers {
+            assert!(minimise(optimizer.as_mut(), 300).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_sgd_step_and_weight_decay() {
+        let mut weights: ArrayD<f32> = arr2(&[[1.0]]).into_dyn();
+        let mut bias: ArrayD<f32> = arr1(&[1.0]).into_dyn();
+        let mut sgd = Sgd::new(0.1).with_weight_decay(0.5);
+        let grads = [arr2(&[[2.0]]).into_dyn(), arr1(&[2.0]).into_dyn()];
+        sgd.step(&mut [weights.view_mut(), bias.view_mut()], &grads);
+        // Decay to 0.95, then step by 0.1 * 2; the bias only takes the step.
+        assert!((weights[[0, 0]] - 0.75).abs() < 1e-6);
+        assert!((bias[[0]] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_schedules() {
+        let step = Schedule::Step {
+            every: 2,
+            gamma: 0.5,
+        };
+        assert_eq!(step.rate(1.0, 1), 1.0);
+        assert_eq!(step.rate(1.0, 4), 0.25);
+        assert_eq!(Schedule::Exponential { gamma: 0.5 }.rate(2.0, 3), 0.25);
+
+        let cosine = Schedule::Cosine {
+            epochs: 10,
+            min_rate: 0.0,
+        };
+        assert_eq!(cosine.rate(1.0, 0), 1.0);
+        assert!((cosine.rate(1.0, 5) - 0.5).abs() < 1e-6);
+        assert!(cosine.rate(1.0, 10).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,170 @@
+// Data parallel training and inference on scoped threads. A batch is cut into one
+// contiguous shard of rows per thread; every thread runs the shared network on
+// its shard, and the results are put back together in row order.
+
+use ndarray::{concatenate, s, Array2, ArrayD, Axis};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::ops::Range;
+use std::thread;
+
+use crate::network::{ForwardPass, NeuralNetwork};
+
+// Splits `rows` into at most `threads` contiguous, nearly equal ranges.
+fn shards(rows: usize, threads: usize) -> Vec<Range<usize>> {
+    let chunk = rows.div_ceil(threads.max(1)).max(1);
+    (0..rows)
+        .step_by(chunk)
+        .map(|start| start..(start + chunk).min(rows))
+        .collect()
+}
+
+impl NeuralNetwork {
+    // Inference output for `input`, with its rows shared out over `threads` threads.
+    pub fn predict(&self, input: &Array2<f32>, threads: usize) -> Array2<f32> {
+        let ranges = shards(input.nrows(), threads);
+        if ranges.len() <= 1 {
+            return self.forward(input).output().clone();
+        }
+        let outputs: Vec<Array2<f32>> = thread::scope(|scope| {
+            let handles: Vec<_> = ranges
+                .into_iter()
+                .map(|rows| {
+                    let shard = input.slice(s![rows, ..]).to_owned();
+                    scope.spawn(move || self.forward(&shard).output().clone())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let views: Vec<_> = outputs.iter().map(|output| output.view()).collect();
+        concatenate(Axis(0), &views).unwrap()
+    }
+
+    // `train_batch` with the forward and backward passes of each shard on its own
+    // thread. Returns the network output for the batch.
+    pub fn train_batch_parallel(
+        &mut self,
+        input: &Array2<f32>,
+        target: &Array2<f32>,
+        threads: usize,
+    ) -> Array2<f32> {
+        let (output, grads) = self.batch_gradients(input, target, threads);
+        self.apply_gradients(&grads);
+        output
+    }
+
+    // The training-mode output and gradient for a batch, computed in shards on
+    // `threads` threads, with the batch normalization running statistics updated
+    // but no optimizer step. The shard gradients are averaged, weighted by their
+    // rows, into exactly the batch gradient. The exception is batch normalization,
+    // which uses each shard's own statistics.
+    pub fn batch_gradients(
+        &mut self,
+        input: &Array2<f32>,
+        target: &Array2<f32>,
+        threads: usize,
+    ) -> (Array2<f32>, Vec<ArrayD<f32>>) {
+        let ranges = shards(input.nrows(), threads);
+        // Every shard gets its own generator for dropout, drawn from the network's
+        // so seeded runs still repeat. A single shard uses the network's directly.
+        if ranges.len() <= 1 {
+            let (mut pass, grads) = self.training_gradients(input, target);
+            return (pass.outputs.pop().unwrap(), grads);
+        }
+        let seeds: Vec<u64> = ranges.iter().map(|_| self.rng.next_u64()).collect();
+        let total = input.nrows() as f32;
+
+        let network = &*self;
+        let results: Vec<(ForwardPass, Vec<ArrayD<f32>>)> = thread::scope(|scope| {
+            let handles: Vec<_> = ranges
+                .into_iter()
+                .zip(seeds)
+                .map(|(rows, seed)| {
+                    scope.spawn(move || {
+                        let input = input.slice(s![rows.clone(), ..]).to_owned();
+                        let target = target.slice(s![rows, ..]).to_owned();
+                        let pass =
+                            network.forward_training(&input, &mut StdRng::seed_from_u64(seed));
+                        let mut grads = network.backward(&input, &pass, &target);
+                        let weight = input.nrows() as f32 / total;
+                        for grad in &mut grads {
+                            *grad *= weight;
+                        }
+                        (pass, grads)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut results = results.into_iter();
+        let (first, mut grads) = results.next().unwrap();
+        let mut outputs = vec![first.output().clone()];
+        self.update_running_statistics(&first);
+        for (pass, shard_grads) in results {
+            for (total, grad) in grads.iter_mut().zip(shard_grads) {
+                *total += &grad;
+            }
+            outputs.push(pass.output().clone());
+            self.update_running_statistics(&pass);
+        }
+
+        let views: Vec<_> = outputs.iter().map(|output| output.view()).collect();
+        (concatenate(Axis(0), &views).unwrap(), grads)
+    }
+}
+
+// The L2 norm of a whole gradient, taken over every parameter at once.
+pub fn gradient_norm(grads: &[ArrayD<f32>]) -> f32 {
+    grads
+        .iter()
+        .map(|grad| grad.iter().map(|g| g * g).sum::<f32>())
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::Activation;
+    use crate::loss::Loss;
+
+    #[test]
+    fn test_shards_cover_every_row_once() {
+        assert_eq!(shards(10, 3), vec![0..4, 4..8, 8..10]);
+        assert_eq!(shards(2, 4), vec![0..1, 1..2]);
+        assert!(shards(0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let input = Array2::from_shape_fn((13, 4), |(i, j)| ((i * 5 + j * 3) % 7) as f32 / 7.0);
+        let target = Array2::from_shape_fn((13, 3), |(i, j)| (i % 3 == j) as u8 as f32);
+        let network = || {
+            NeuralNetwork::new(&[4, 6, 3], 0.5)
+                .with_activations(&[Activation::Tanh, Activation::Softmax])
+                .with_loss(Loss::SoftmaxCrossEntropy)
+                .with_penalty(0.0, 0.01)
+                .with_seed(5)
+        };
+
+        let mut sequential = network();
+        let mut parallel = network();
+        assert_eq!(
+            parallel.predict(&input, 4),
+            *sequential.forward(&input).output()
+        );
+
+        let output = parallel.train_batch_parallel(&input, &target, 3);
+        assert_eq!(output, *sequential.train_batch(&input, &target).output());
+        for (a, b) in parallel
+            .parameters_mut()
+            .iter()
+            .zip(sequential.parameters_mut().iter())
+        {
+            for (x, y) in a.iter().zip(b.iter()) {
+                assert!((x - y).abs() < 1e-5);
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// Hyperparameter search. A search space lists the values to try for each
+// setting; a grid search trains every combination and a random search samples a
+// fixed number of them. Each trial trains a fresh network on the training split,
+// is scored on the validation split, and the results are ranked into a
+// leaderboard.
+
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+use std::time::Instant;
+
+use crate::activation::Activation;
+use crate::init::Initializer;
+use crate::loss::Loss;
+use crate::network::NeuralNetwork;
+use crate::optimizer;
+use crate::train::TrainConfig;
+
+pub struct SearchSpace {
+    pub hidden: Vec<Vec<usize>>,
+    pub learning_rates: Vec<f32>,
+    pub activations: Vec<Activation>,
+    pub optimizers: Vec<String>,
+    pub batch_sizes: Vec<usize>,
+    pub dropout: Vec<f32>,
+    pub l2: Vec<f32>,
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        SearchSpace {
+            hidden: vec![vec![128], vec![256, 128], vec![512, 256]],
+            learning_rates: vec![0.0003, 0.001, 0.003],
+            activations: vec![Activation::Relu, Activation::Sigmoid],
+            optimizers: vec!["adam".to_string()],
+            batch_sizes: vec![32],
+            dropout: vec![0.0],
+            l2: vec![0.0],
+        }
+    }
+}
+
+// One configuration to train. `seed` fixes its weights, shuffling and dropout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trial {
+    pub hidden: Vec<usize>,
+    pub learning_rate: f32,
+    pub activation: Activation,
+    pub optimizer: String,
+    pub batch_size: usize,
+    pub dropout: f32,
+    pub l2: f32,
+    pub seed: u64,
+}
+
+impl SearchSpace {
+    // Every combination, with trial i seeded `seed + i`.
+    pub fn grid(&self, seed: u64) -> Vec<Trial> {
+        let mut trials = Vec::new();
+        for hidden in &self.hidden {
+            for &learning_rate in &self.learning_rates {
+                for &activation in &self.activations {
+                    for optimizer in &self.optimizers {
+                        for &batch_size in &self.batch_sizes {
+                            for &dropout in &self.dropout {
+                                for &l2 in &self.l2 {
+                                    trials.push(Trial {
+                                        hidden: hidden.clone(),
+                                        learning_rate,
+                                        activation,
+                                        optimizer: optimizer.clone(),
+                                        batch_size,
+                                        dropout,
+                                        l2,
+                                        seed: seed + trials.len() as u64,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        trials
+    }
+
+    // `count` trials with each setting picked at random from its list, except the
+    // learning rate, which is drawn log-uniformly between the smallest and largest
+    // listed rates. The same seed gives the same trials.
+    pub fn random(&self, count: usize, seed: u64) -> Vec<Trial> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (low, high) = self
+            .learning_rates
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &r| (lo.min(r), hi.max(r)));
+        (0..count)
+            .map(|i| Trial {
+                hidden: self.hidden.choose(&mut rng).unwrap().clone(),
+                learning_rate: if low < high {
+                    (rng.gen_range(low.ln()..=high.ln())).exp()
+                } else {
+                    low
+                },
+                activation: *self.activations.choose(&mut rng).unwrap(),
+                optimizer: self.optimizers.choose(&mut rng).unwrap().clone(),
+                batch_size: *self.batch_sizes.choose(&mut rng).unwrap(),
+                dropout: *self.dropout.choose(&mut rng).unwrap(),
+                l2: *self.l2.choose(&mut rng).unwrap(),
+                seed: seed + i as u64,
+            })
+            .collect()
+    }
+}
+
+impl Trial {
+    // A classifier for `inputs` features and `classes` classes with a softmax
+    // output trained on cross-entropy. Relu-family hidden layers start from He
+    // initialization, everything else from Xavier.
+    pub fn build(&self, inputs: usize, classes: usize) -> Result<NeuralNetwork, Box<dyn Error>> {
+        let mut sizes = vec![inputs];
+        sizes.extend(&self.hidden);
+        sizes.push(classes);
+
+        let mut activations = vec![self.activation; self.hidden.len()];
+        activations.push(Activation::Softmax);
+        let hidden_init = match self.activation {
+            Activation::Relu | Activation::LeakyRelu(_) => Initializer::HeNormal,
+            _ => Initializer::XavierUniform,
+        };
+        let mut initializers = vec![hidden_init; self.hidden.len()];
+        initializers.push(Initializer::XavierUniform);
+
+        let mut network = NeuralNetwork::new(&sizes, self.learning_rate)
+            .with_activations(&activations)
+            .with_loss(Loss::SoftmaxCrossEntropy)
+            .with_penalty(0.0, self.l2)
+            .with_optimizer(optimizer::by_name(
+                &self.optimizer,
+                self.learning_rate,
+                0.0,
+            )?)
+            .with_initializers(&initializers);
+        if self.dropout > 0.0 {
+            network = network.with_dropout(self.dropout);
+        }
+        Ok(network.with_seed(self.seed))
+    }
+
+    pub fn hidden_sizes(&self) -> String {
+        let sizes: Vec<String> = self.hidden.iter().map(|n| n.to_string()).collect();
+        sizes.join("-")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrialResult {
+    pub trial: Trial,
+    // The epoch with the lowest validation loss, whose weights are kept.
+    pub best_epoch: usize,
+    pub validation_loss: f32,
+    pub validation_accuracy: f32,
+    pub seconds: f32,
+}
+
+// Trains every trial for up to `config.epochs` epochs (its batch size replaces the
+// config's), restoring the epoch with the lowest validation loss, and returns the
+// results best first: highest validation accuracy, then lowest loss.
+// `on_trial` sees each result as it finishes.
+pub fn search<F>(
+    trials: &[Trial],
+    train: (&Array2<f32>, &Array2<f32>),
+    validation: (&Array2<f32>, &Array2<f32>),
+    config: &TrainConfig,
+    mut on_trial: F,
+) -> Result<Vec<TrialResult>, Box<dyn Error>>
+where
+    F: FnMut(&TrialResult),
+{
+    let (features, labels) = train;
+    let mut results = Vec::with_capacity(trials.len());
+    for trial in trials {
+        let start = Instant::now();
+        let mut network = trial.build(features.ncols(), labels.ncols())?;
+        let config = TrainConfig {
+            batch_size: trial.batch_size,
+            restore_best: true,
+            progress: false,
+            ..*config
+        };
+        let history = network.fit_with_validation(features, labels, validation, &config, |_| {});
+        let best_epoch = history
+            .iter()
+            .filter_map(|stats| stats.validation_loss.map(|loss| (stats.epoch, loss)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(epoch, _)| epoch);
+        let (validation_loss, validation_accuracy) =
+            network.score_parallel(validation.0, validation.1, config.threads);
+
+        let result = TrialResult {
+            trial: trial.clone(),
+            best_epoch,
+            validation_loss,
+            validation_accuracy,
+            seconds: start.elapsed().as_secs_f32(),
+        };
+        on_trial(&result);
+        results.push(result);
+    }
+    results.sort_by(|a, b| {
+        b.validation_accuracy
+            .total_cmp(&a.validation_accuracy)
+            .then(a.validation_loss.total_cmp(&b.validation_loss))
+    });
+    Ok(results)
+}
+
+// Writes the ranked results as CSV, best first.
+pub fn write_leaderboard(results: &[TrialResult], path: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record([
+        "rank",
+        "validation_accuracy",
+        "validation_loss",
+        "best_epoch",
+        "hidden",
+        "activation",
+        "optimizer",
+        "learning_rate",
+        "batch_size",
+        "dropout",
+        "l2",
+        "seed",
+        "seconds",
+    ])?;
+    for (rank, result) in results.iter().enumerate() {
+        let trial = &result.trial;
+        writer.write_record([
+            (rank + 1).to_string(),
+            format!("{:.2}", result.validation_accuracy),
+            format!("{:.4}", result.validation_loss),
+            result.best_epoch.to_string(),
+            trial.hidden_sizes(),
+            format!("{:?}", trial.activation),
+            trial.optimizer.clone(),
+            trial.learning_rate.to_string(),
+            trial.batch_size.to_string(),
+            trial.dropout.to_string(),
+            trial.l2.to_string(),
+            trial.seed.to_string(),
+            format!("{:.1}", result.seconds),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> SearchSpace {
+        SearchSpace {
+            hidden: vec![vec![4], vec![6, 4]],
+            learning_rates: vec![0.01, 0.1],
+            activations: vec![Activation::Relu, Activation::Tanh],
+            optimizers: vec!["adam".to_string()],
+            batch_sizes: vec![8],
+            dropout: vec![0.0],
+            l2: vec![0.0, 0.001],
+        }
+    }
+
+    #[test]
+    fn test_grid_and_random_trials() {
+        let grid = space().grid(10);
+        assert_eq!(grid.len(), 16);
+        assert_eq!(grid[3].seed, 13);
+        assert_ne!(grid[0], grid[1]);
+
+        let random = space().random(5, 1);
+        assert_eq!(random, space().random(5, 1));
+        assert!(random
+            .iter()
+            .all(|trial| (0.01..=0.1).contains(&trial.learning_rate)));
+    }
+
+    #[test]
+    fn test_search_ranks_trials() {
+        let features = Array2::from_shape_fn((48, 2), |(i, j)| {
+            let class = (i % 2) as f32;
+            if j == 0 {
+                class
+            } else {
+                1.0 - class
+            }
+        });
+        let labels = Array2::from_shape_fn((48, 2), |(i, j)| if i % 2 == j { 1.0 } else { 0.0 });
+        let trials = space().random(3, 4);
+        let config = TrainConfig {
+            epochs: 5,
+            ..TrainConfig::default()
+        };
+
+        let mut seen = 0;
+        let results = search(
+            &trials,
+            (&features, &labels),
+            (&features, &labels),
+            &config,
+            |_| seen += 1,
+        )
+        .unwrap();
+        assert_eq!((seen, results.len()), (3, 3));
+        for pair in results.windows(2) {
+            assert!(pair[0].validation_accuracy >= pair[1].validation_accuracy);
+        }
+        assert!((1..=5).contains(&results[0].best_epoch));
+    }
+}
@@ -0,0 +1,335 @@
+use ndarray::{Array2, Axis};
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::augment::Augmentation;
+use crate::evaluate::{argmax, Evaluation};
+use crate::layer::Layer;
+use crate::metrics::ProgressBar;
+use crate::network::NeuralNetwork;
+use crate::optimizer::Schedule;
+use crate::parallel::gradient_norm;
+
+pub struct TrainConfig {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub shuffle: bool,
+    pub schedule: Schedule,
+    // Stop once the validation loss has not improved for this many epochs. Only
+    // used when training with a validation set.
+    pub patience: Option<usize>,
+    // Put back the weights from the epoch with the lowest validation loss when
+    // training ends.
+    pub restore_best: bool,
+    // Threads to share each batch between; see `train_batch_parallel`.
+    pub threads: usize,
+    // Show a progress bar with the running loss and time left on stderr.
+    pub progress: bool,
+    // Random distortions applied to the images of every training batch.
+    pub augment: Option<Augmentation>,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            epochs: 3,
+            batch_size: 32,
+            shuffle: true,
+            schedule: Schedule::Constant,
+            patience: None,
+            restore_best: true,
+            threads: 1,
+            progress: false,
+            augment: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EpochStats {
+    pub epoch: usize,
+    pub loss: f32,
+    pub accuracy: f32,
+    pub learning_rate: f32,
+    pub validation_loss: Option<f32>,
+    pub validation_accuracy: Option<f32>,
+    // Wall-clock time for the epoch, including validation.
+    pub seconds: f32,
+    // The L2 norm of the batch gradient (before the optimizer step), averaged over
+    // the epoch's batches.
+    pub gradient_norm: f32,
+}
+
+impl NeuralNetwork {
+    // Trains on shuffled mini-batches, averaging the gradient over each batch, and
+    // calls `on_epoch` after every pass over the data. The schedule scales the
+    // optimizer's learning rate as it was when `fit` was called.
+    pub fn fit<F>(
+        &mut self,
+        features: &Array2<f32>,
+        labels: &Array2<f32>,
+        config: &TrainConfig,
+        on_epoch: F,
+    ) -> Vec<EpochStats>
+    where
+        F: FnMut(&EpochStats),
+    {
+        self.train(features, labels, None, config, on_epoch)
+    }
+
+    // Like `fit`, but also scores a held-out set after every epoch, which is what
+    // early stopping and restoring the best weights are based on.
+    pub fn fit_with_validation<F>(
+        &mut self,
+        features: &Array2<f32>,
+        labels: &Array2<f32>,
+        validation: (&Array2<f32>, &Array2<f32>),
+        config: &TrainConfig,
+        on_epoch: F,
+    ) -> Vec<EpochStats>
+    where
+        F: FnMut(&EpochStats),
+    {
+        self.train(features, labels, Some(validation), config, on_epoch)
+    }
+
+    // Loss and accuracy (as a percentage) over a whole dataset, without training.
+    // The loss leaves out the weight penalties, so it measures the fit alone.
+    pub fn score(&self, features: &Array2<f32>, labels: &Array2<f32>) -> (f32, f32) {
+        self.score_parallel(features, labels, 1)
+    }
+
+    pub fn score_parallel(
+        &self,
+        features: &Array2<f32>,
+        labels: &Array2<f32>,
+        threads: usize,
+    ) -> (f32, f32) {
+        let output = self.predict(features, threads);
+        let loss = self.loss().value(&output, labels);
+        (loss, Evaluation::new(&output, labels).accuracy())
+    }
+
+    fn train<F>(
+        &mut self,
+        features: &Array2<f32>,
+        labels: &Array2<f32>,
+        validation: Option<(&Array2<f32>, &Array2<f32>)>,
+        config: &TrainConfig,
+        mut on_epoch: F,
+    ) -> Vec<EpochStats>
+    where
+        F: FnMut(&EpochStats),
+    {
+        let total = features.nrows();
+        let mut order: Vec<usize> = (0..total).collect();
+        let mut history = Vec::with_capacity(config.epochs);
+        let base_rate = self.optimizer().learning_rate();
+
+        let mut best: Option<(f32, Vec<Layer>)> = None;
+        let mut epochs_without_improvement = 0;
+
+        for epoch in 0..config.epochs {
+            let learning_rate = config.schedule.rate(base_rate, epoch);
+            self.optimizer_mut().set_learning_rate(learning_rate);
+            if config.shuffle {
+                order.shuffle(&mut self.rng);
+            }
+
+            let start = Instant::now();
+            let batches = order.chunks(config.batch_size.max(1));
+            let progress = config.progress.then(|| ProgressBar::new(batches.len()));
+            let mut correct = 0;
+            let mut loss = 0.0;
+            let mut norms = 0.0;
+            for (i, batch) in batches.enumerate() {
+                let input = features.select(Axis(0), batch);
+                let input = match &config.augment {
+                    Some(augmentation) => augmentation.batch(&input, &mut self.rng),
+                    None => input,
+                };
+                let target = labels.select(Axis(0), batch);
+
+                // The penalty is taken with the gradients, before the step moves
+                // the weights, so it matches the data loss of the same pass.
+                let (output, grads) = self.batch_gradients(&input, &target, config.threads);
+                let penalty = self.penalty_value();
+                norms += gradient_norm(&grads);
+                self.apply_gradients(&grads);
+
+                loss += (self.loss().value(&output, &target) + penalty) * batch.len() as f32;
+
+                correct += output
+                    .outer_iter()
+                    .zip(target.outer_iter())
+                    .filter(|(predicted, actual)| argmax(predicted.view()) == argmax(actual.view()))
+                    .count();
+                if let Some(progress) = &progress {
+                    let seen = (i * config.batch_size.max(1) + batch.len()) as f32;
+                    progress.update(i + 1, loss / seen);
+                }
+            }
+            if let Some(progress) = &progress {
+                progress.finish();
+            }
+            let batch_count = total.div_ceil(config.batch_size.max(1)).max(1);
+
+            let scores = validation
+                .map(|(features, labels)| self.score_parallel(features, labels, config.threads));
+            let stats = EpochStats {
+                epoch: epoch + 1,
+                loss: loss / total as f32,
+                accuracy: correct as f32 / total as f32 * 100.0,
+                learning_rate,
+                validation_loss: scores.map(|s| s.0),
+                validation_accuracy: scores.map(|s| s.1),
+                seconds: start.elapsed().as_secs_f32(),
+                gradient_norm: norms / batch_count as f32,
+            };
+            on_epoch(&stats);
+            history.push(stats);
+
+            if let Some((validation_loss, _)) = scores {
+                if best
+                    .as_ref()
+                    .is_none_or(|(lowest, _)| validation_loss < *lowest)
+                {
+                    let layers = if config.restore_best {
+                        self.layers.clone()
+                    } else {
+                        Vec::new()
+                    };
+                    best = Some((validation_loss, layers));
+                    epochs_without_improvement = 0;
+                } else {
+                    epochs_without_improvement += 1;
+                }
+                if config
+                    .patience
+                    .is_some_and(|patience| epochs_without_improvement >= patience)
+                {
+                    break;
+                }
+            }
+        }
+
+        // Only the weights are restored; the optimizer keeps its latest state.
+        if let Some((_, layers)) = best {
+            if config.restore_best {
+                self.layers = layers;
+            }
+        }
+        self.optimizer_mut().set_learning_rate(base_rate);
+        history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_reports_every_epoch() {
+        // Two well separated classes that a small network learns quickly.
+        let features = Array2::from_shape_fn((40, 2), |(i, j)| {
+            let class = (i % 2) as f32;
+            if j == 0 {
+                class
+            } else {
+                1.0 - class
+            }
+        });
+        let labels = Array2::from_shape_fn((40, 2), |(i, j)| if i % 2 == j { 1.0 } else { 0.0 });
+
+        let mut network = NeuralNetwork::new(&[2, 4, 2], 5.0).with_seed(4);
+        let config = TrainConfig {
+            epochs: 30,
+            batch_size: 8,
+            shuffle: true,
+            ..TrainConfig::default()
+        };
+        let mut seen = Vec::new();
+        let history = network.fit(&features, &labels, &config, |stats| seen.push(stats.epoch));
+
+        assert_eq!(seen, (1..=30).collect::<Vec<_>>());
+        assert_eq!(history.last().unwrap().accuracy, 100.0);
+    }
+
+    #[test]
+    fn test_seeded_runs_are_identical() {
+        let features =
+            Array2::from_shape_fn((30, 3), |(i, j)| ((i * 7 + j * 5) % 11) as f32 / 10.0);
+        let labels = Array2::from_shape_fn((30, 2), |(i, j)| if i % 2 == j { 1.0 } else { 0.0 });
+        let config = TrainConfig {
+            epochs: 1,
+            batch_size: 4,
+            shuffle: true,
+            ..TrainConfig::default()
+        };
+        let run = |seed| {
+            let mut network = NeuralNetwork::new(&[3, 6, 5, 2], 0.5)
+                .with_dropout(0.3)
+                .with_batch_norm()
+                .with_seed(seed);
+            network.fit(&features, &labels, &config, |_| {});
+            network
+                .parameters_mut()
+                .into_iter()
+                .flat_map(|param| param.iter().map(|w| w.to_bits()).collect::<Vec<_>>())
+                .collect::<Vec<u32>>()
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn test_fit_follows_the_schedule() {
+        let features = Array2::zeros((4, 2));
+        let labels = Array2::from_elem((4, 2), 0.5);
+
+        let mut network = NeuralNetwork::new(&[2, 2], 1.0);
+        let config = TrainConfig {
+            epochs: 4,
+            batch_size: 4,
+            shuffle: false,
+            schedule: Schedule::Step {
+                every: 2,
+                gamma: 0.1,
+            },
+            ..TrainConfig::default()
+        };
+        let history = network.fit(&features, &labels, &config, |_| {});
+
+        let rates: Vec<f32> = history.iter().map(|stats| stats.learning_rate).collect();
+        assert_eq!(rates, vec![1.0, 1.0, 0.1, 0.1]);
+        assert_eq!(network.optimizer().learning_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_early_stopping_restores_the_best_weights() {
+        // The validation labels are the training labels swapped, so the validation
+        // loss gets worse as soon as the network starts to learn.
+        let features = Array2::from_shape_fn((40, 2), |(i, j)| ((i + j) % 2) as f32);
+        let labels = Array2::from_shape_fn((40, 2), |(i, j)| if i % 2 == j { 1.0 } else { 0.0 });
+        let swapped = Array2::from_shape_fn((40, 2), |(i, j)| if i % 2 == j { 0.0 } else { 1.0 });
+
+        let mut network = NeuralNetwork::new(&[2, 4, 2], 5.0).with_seed(4);
+        let config = TrainConfig {
+            epochs: 50,
+            batch_size: 8,
+            patience: Some(2),
+            ..TrainConfig::default()
+        };
+        let history =
+            network.fit_with_validation(&features, &labels, (&features, &swapped), &config, |_| {});
+
+        assert!(history.len() < 50);
+        let lowest = history
+            .iter()
+            .filter_map(|stats| stats.validation_loss)
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(network.score(&features, &swapped).0, lowest);
+    }
+}